@@ -1,3 +1,134 @@
+use std::error::Error as StdError;
+use self::super::Error;
+use std::str::FromStr;
+use std::ops::Deref;
+use std::fmt;
+use idna;
+
+
+/// A validated OpenAlias, normalised to lowercase A-labels, for storing and comparing.
+///
+/// Whether it was written email-style (`donate@getmonero.org`) or FQDN-style (`donate.getmonero.org`) is remembered,
+/// and two aliases are only equal if they were written alike.
+///
+/// Dereferences to the normalised alias, so can be passed to the lookup functions directly.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{OpenAlias, addresses_with, Error};
+/// let alias: OpenAlias = "Donate@GetMonero.org".parse().unwrap();
+/// assert_eq!(alias.to_string(), "donate@getmonero.org");
+/// assert_eq!(alias.local_part(), Some("donate"));
+/// assert_eq!(alias.domain(), "getmonero.org");
+/// assert_eq!(alias.fqdn(), "donate.getmonero.org.");
+/// assert!(alias.is_email_style());
+///
+/// let alias: OpenAlias = "donate.getmonero.org.".parse().unwrap();
+/// assert_eq!(alias.to_string(), "donate.getmonero.org");
+/// assert_eq!(alias.local_part(), None);
+/// assert_eq!(alias.domain(), "donate.getmonero.org");
+///
+/// let alias: OpenAlias = "datki@Żółw.pl".parse().unwrap();
+/// assert_eq!(alias.to_string(), "datki@xn--w-uga1v8h.pl");
+/// assert_eq!(alias.to_unicode(), "datki@żółw.pl");
+///
+/// let dotted: OpenAlias = "a\u{3002}b@Żółw.pl".parse().unwrap();
+/// assert_eq!(dotted.local_part(), Some("a.b"));
+/// assert_eq!(dotted.domain(), "xn--w-uga1v8h.pl");
+///
+/// assert!("a..b".parse::<OpenAlias>().is_err());
+/// assert!("foo@".parse::<OpenAlias>().is_err());
+/// assert!("a.b@".parse::<OpenAlias>().is_err());
+///
+/// let fake = |fqdn: &str| -> Result<Vec<Vec<u8>>, Error> {
+///     assert_eq!(fqdn, "datki.xn--w-uga1v8h.pl.");
+///     Ok(vec![b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;".to_vec()])
+/// };
+/// assert_eq!(addresses_with(&fake, &alias).unwrap().len(), 1);
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OpenAlias {
+    alias: String,
+    at: Option<usize>,
+}
+
+impl OpenAlias {
+    /// Get the part before the `@`, if email-style.
+    pub fn local_part(&self) -> Option<&str> {
+        self.at.map(|at| &self.alias[..at])
+    }
+
+    /// Get the part after the `@` if email-style, or the whole alias otherwise.
+    pub fn domain(&self) -> &str {
+        match self.at {
+            Some(at) => &self.alias[at + 1..],
+            None => &self.alias,
+        }
+    }
+
+    /// Check whether the alias was written email-style, i.e. with an `@`.
+    pub fn is_email_style(&self) -> bool {
+        self.at.is_some()
+    }
+
+    /// Get the FQDN looked up for the alias.
+    pub fn fqdn(&self) -> String {
+        format!("{}.", self.alias.replace("@", "."))
+    }
+
+    /// Get the alias with U-labels, for display.
+    pub fn to_unicode(&self) -> String {
+        alias_to_unicode(&self.alias)
+    }
+}
+
+impl FromStr for OpenAlias {
+    type Err = Error;
+
+    /// Validate and normalise an alias, as per [`alias_to_fqdn_strict()`](fn.alias_to_fqdn_strict.html).
+    fn from_str(s: &str) -> Result<OpenAlias, Error> {
+        let fqdn = alias_to_fqdn_strict(s)?;
+        let mut alias = fqdn[..fqdn.len() - 1].to_lowercase();
+        let at = match s.find('@') {
+            Some(at) => {
+                // IDNA mapping can turn other full stops, like "。", into '.'
+                let local_part = if s.is_ascii() {
+                    s[..at].to_string()
+                } else {
+                    idna::domain_to_ascii(&s[..at]).map_err(|_| FqdnError::Idna)?
+                };
+                let at = alias.match_indices('.').nth(local_part.matches('.').count()).ok_or(FqdnError::EmptyDomain)?.0;
+                alias.replace_range(at..at + 1, "@");
+                Some(at)
+            }
+            None => None,
+        };
+        Ok(OpenAlias { alias, at })
+    }
+}
+
+impl fmt::Display for OpenAlias {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.alias)
+    }
+}
+
+impl Deref for OpenAlias {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.alias
+    }
+}
+
+impl AsRef<str> for OpenAlias {
+    fn as_ref(&self) -> &str {
+        &self.alias
+    }
+}
+
+
 /// Convert an OpenAlias to an FQDN.
 ///
 /// Paraphrasing [OpenAlias](https://openalias.org#implement):
@@ -8,6 +139,9 @@
 ///
 /// 3. Append, if one doesn't exist, a dot to the end of the alias, to ensure it's an FQDN.
 ///
+/// Internationalised aliases are then converted to A-labels (punycode) with UTS #46 processing, as the DNS needs;
+/// see [`alias_to_unicode()`](fn.alias_to_unicode.html) for the other way around.
+///
 /// # Examples
 ///
 /// ```
@@ -19,10 +153,16 @@
 /// assert_eq!(alias_to_fqdn("nabijaczleweli.xyz."),
 ///            Some("nabijaczleweli.xyz.".to_string()));
 ///
+/// assert_eq!(alias_to_fqdn("datki@Żółw.pl"),
+///            Some("datki.xn--w-uga1v8h.pl.".to_string()));
+///
 /// assert_eq!(alias_to_fqdn("nabijaczleweli"), None);
 /// ```
 pub fn alias_to_fqdn(alias: &str) -> Option<String> {
     let mut alias = alias.replace("@", ".");
+    if !alias.is_ascii() {
+        alias = idna::domain_to_ascii(&alias).ok()?;
+    }
     if alias.contains('.') {
         if !alias.ends_with('.') {
             alias.push('.');
@@ -32,3 +172,126 @@ pub fn alias_to_fqdn(alias: &str) -> Option<String> {
         None
     }
 }
+
+/// Convert an OpenAlias to an FQDN, like [`alias_to_fqdn()`](fn.alias_to_fqdn.html), but also check that the result is
+/// a valid domain name, as per RFC 1035 (with `_` allowed, as in service names), after conversion to A-labels.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{FqdnError, alias_to_fqdn_strict};
+/// assert_eq!(alias_to_fqdn_strict("donate@nabijaczleweli.xyz"),
+///            Ok("donate.nabijaczleweli.xyz.".to_string()));
+///
+/// assert_eq!(alias_to_fqdn_strict("datki@żółw.pl"),
+///            Ok("datki.xn--w-uga1v8h.pl.".to_string()));
+///
+/// assert_eq!(alias_to_fqdn_strict("nabijaczleweli"), Err(FqdnError::NoDot));
+/// assert_eq!(alias_to_fqdn_strict("a@xn--ż.pl"), Err(FqdnError::Idna));
+/// assert_eq!(alias_to_fqdn_strict("a@b@c.com"), Err(FqdnError::MultipleAt));
+/// assert_eq!(alias_to_fqdn_strict("donate@"), Err(FqdnError::EmptyDomain));
+/// assert_eq!(alias_to_fqdn_strict("a.b@"), Err(FqdnError::EmptyDomain));
+/// assert_eq!(alias_to_fqdn_strict("a..b"), Err(FqdnError::EmptyLabel(1)));
+/// assert_eq!(alias_to_fqdn_strict("-bad-.com"), Err(FqdnError::HyphenAtEdge("-bad-".to_string())));
+/// assert_eq!(alias_to_fqdn_strict("don ate@example.com"), Err(FqdnError::BadCharacter(' ', 3)));
+/// assert_eq!(alias_to_fqdn_strict(&format!("{}.com", "a".repeat(64))), Err(FqdnError::LabelTooLong("a".repeat(64))));
+/// assert_eq!(alias_to_fqdn_strict(&format!("{}com", "ab.".repeat(84))), Err(FqdnError::NameTooLong(255)));
+/// ```
+pub fn alias_to_fqdn_strict(alias: &str) -> Result<String, FqdnError> {
+    if alias.matches('@').count() > 1 {
+        return Err(FqdnError::MultipleAt);
+    }
+    if alias.ends_with('@') {
+        return Err(FqdnError::EmptyDomain);
+    }
+    let alias = if alias.is_ascii() {
+        alias.to_string()
+    } else {
+        idna::domain_to_ascii(&alias.replace("@", ".")).map_err(|_| FqdnError::Idna)?
+    };
+    if let Some((offset, c)) = alias.char_indices().find(|&(_, c)| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '@')) {
+        return Err(FqdnError::BadCharacter(c, offset));
+    }
+
+    let fqdn = alias_to_fqdn(&alias).ok_or(FqdnError::NoDot)?;
+    let name = &fqdn[..fqdn.len() - 1];
+    for (i, label) in name.split('.').enumerate() {
+        if label.is_empty() {
+            return Err(FqdnError::EmptyLabel(i));
+        }
+        if label.len() > 63 {
+            return Err(FqdnError::LabelTooLong(label.to_string()));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(FqdnError::HyphenAtEdge(label.to_string()));
+        }
+    }
+    if name.len() > 253 {
+        return Err(FqdnError::NameTooLong(name.len()));
+    }
+
+    Ok(fqdn)
+}
+
+/// Convert an OpenAlias or FQDN's A-labels (punycode) to U-labels for display, leaving it as-is if they're not valid.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::alias_to_unicode;
+/// assert_eq!(alias_to_unicode("datki@xn--w-uga1v8h.pl"), "datki@żółw.pl");
+/// assert_eq!(alias_to_unicode("datki.xn--w-uga1v8h.pl."), "datki.żółw.pl.");
+/// assert_eq!(alias_to_unicode("donate@getmonero.org"), "donate@getmonero.org");
+/// ```
+pub fn alias_to_unicode(alias: &str) -> String {
+    let (user, domain) = match alias.rfind('@') {
+        Some(at) => alias.split_at(at + 1),
+        None => ("", alias),
+    };
+    match idna::domain_to_unicode(domain) {
+        (domain, Ok(())) => format!("{}{}", user, domain),
+        (_, Err(_)) => alias.to_string(),
+    }
+}
+
+
+/// What's wrong with an alias, as found by [`alias_to_fqdn_strict()`](fn.alias_to_fqdn_strict.html).
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum FqdnError {
+    /// More than one `@`.
+    MultipleAt,
+    /// Nothing after the `@`.
+    EmptyDomain,
+    /// No `.`, so it's an address, not an alias.
+    NoDot,
+    /// Not convertible to A-labels, see [UTS #46](https://www.unicode.org/reports/tr46/).
+    Idna,
+    /// The label with the specified index is empty, e.g. "a..b".
+    EmptyLabel(usize),
+    /// The specified character, at the specified byte offset, isn't a letter, digit, `-`, `_`, `.` or `@`.
+    BadCharacter(char, usize),
+    /// The specified label starts or ends with a `-`.
+    HyphenAtEdge(String),
+    /// The specified label is longer than 63 octets.
+    LabelTooLong(String),
+    /// The name is the specified number of octets long, over 253 (excluding the final dot).
+    NameTooLong(usize),
+}
+
+impl fmt::Display for FqdnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FqdnError::MultipleAt => f.write_str("more than one @"),
+            FqdnError::EmptyDomain => f.write_str("nothing after the @"),
+            FqdnError::NoDot => f.write_str("no dot, so not a domain name"),
+            FqdnError::Idna => f.write_str("not a valid internationalised domain name"),
+            FqdnError::EmptyLabel(i) => write!(f, "label {} is empty", i + 1),
+            FqdnError::BadCharacter(c, offset) => write!(f, "{:?} at offset {} is not allowed in domain names", c, offset),
+            FqdnError::HyphenAtEdge(ref label) => write!(f, "label {} starts or ends with a hyphen", label),
+            FqdnError::LabelTooLong(ref label) => write!(f, "label {} is {} octets long, over 63", label, label.len()),
+            FqdnError::NameTooLong(len) => write!(f, "name is {} octets long, over 253", len),
+        }
+    }
+}
+
+impl StdError for FqdnError {}
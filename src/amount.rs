@@ -0,0 +1,160 @@
+use self::super::Error;
+use std::fmt;
+
+
+/// An exact amount of a cryptocurrency, as a whole number of its smallest unit.
+///
+/// Parsed from plain decimal notation only, e.g. "0.5", so that an amount always means the same thing:
+/// exponents, digit grouping, signs, and more decimal places than the smallest unit allows are all refused.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::Amount;
+/// let amount = Amount::parse_for("0.5", "btc").unwrap();
+/// assert_eq!(amount.atomic_units(), 50_000_000);
+/// assert_eq!(amount.decimals(), 8);
+/// assert_eq!(amount.to_string(), "0.5");
+///
+/// assert_eq!(Amount::parse_for("1.000000000001", "xmr").unwrap().atomic_units(), 1_000_000_000_001);
+/// assert_eq!(Amount::parse_for("1", "eth").unwrap(), Amount::from_atomic_units(1_000_000_000_000_000_000, 18));
+/// assert_eq!(Amount::from_atomic_units(1, 12).to_string(), "0.000000000001");
+///
+/// assert_eq!(Amount::parse_for("1e-3", "btc").unwrap_err().to_string(),
+///            "Malformed transaction amount: exponent notation isn't allowed");
+/// assert_eq!(Amount::parse_for("1,000", "btc").unwrap_err().to_string(),
+///            "Malformed transaction amount: digit grouping isn't allowed");
+/// assert_eq!(Amount::parse_for("0.000000001", "btc").unwrap_err().to_string(),
+///            "Malformed transaction amount: 9 decimal places, more than the smallest unit's 8");
+/// assert!(Amount::parse_for("-1", "btc").is_err());
+/// assert!(Amount::parse_for(".5", "btc").is_err());
+/// assert!(Amount::parse_for("1", "zec").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Amount {
+    atomic_units: u128,
+    decimals: u32,
+}
+
+impl Amount {
+    /// Create an amount of the specified number of smallest units, each 10<sup>-`decimals`</sup> of the whole.
+    pub fn from_atomic_units(atomic_units: u128, decimals: u32) -> Amount {
+        Amount {
+            atomic_units,
+            decimals,
+        }
+    }
+
+    /// Parse an amount of a cryptocurrency whose smallest unit is 10<sup>-`decimals`</sup> of the whole.
+    pub fn parse(amount: &str, decimals: u32) -> Result<Amount, Error> {
+        let error = |why: &str| Err(Error::AmountParse(why.to_string()));
+        if amount.is_empty() {
+            return error("empty");
+        }
+        if let Some(c) = amount.chars().find(|&c| !(c.is_ascii_digit() || c == '.')) {
+            return match c {
+                'e' | 'E' => error("exponent notation isn't allowed"),
+                ',' | '_' | '\'' | ' ' => error("digit grouping isn't allowed"),
+                '+' | '-' => error("signs aren't allowed"),
+                c => Err(Error::AmountParse(format!("{:?} isn't a digit or decimal point", c))),
+            };
+        }
+
+        let (whole, fraction) = match amount.find('.') {
+            Some(point) => (&amount[..point], &amount[point + 1..]),
+            None => (amount, ""),
+        };
+        if fraction.contains('.') {
+            return error("more than one decimal point");
+        }
+        if whole.is_empty() || (fraction.is_empty() && amount.ends_with('.')) {
+            return error("digits needed on both sides of the decimal point");
+        }
+        if fraction.len() > decimals as usize {
+            return Err(Error::AmountParse(format!("{} decimal places, more than the smallest unit's {}", fraction.len(), decimals)));
+        }
+
+        let scale = |digits: &str, by: u32| {
+            10u128.checked_pow(by).and_then(|factor| digits.parse::<u128>().ok().and_then(|d| d.checked_mul(factor)))
+        };
+        let atomic_units = scale(whole, decimals)
+            .and_then(|whole| if fraction.is_empty() {
+                Some(whole)
+            } else {
+                scale(fraction, decimals - fraction.len() as u32).and_then(|fraction| whole.checked_add(fraction))
+            })
+            .ok_or_else(|| Error::AmountParse("too large".to_string()))?;
+
+        Ok(Amount::from_atomic_units(atomic_units, decimals))
+    }
+
+    /// Parse an amount of the specified cryptocurrency, which must have a known smallest unit.
+    pub fn parse_for(amount: &str, cryptocurrency: &str) -> Result<Amount, Error> {
+        let cryptocurrency = cryptocurrency.to_lowercase();
+        let decimals = Amount::currency_decimals(&cryptocurrency)
+            .ok_or_else(|| Error::AmountParse(format!("no known smallest unit for {}", cryptocurrency)))?;
+        Amount::parse(amount, decimals)
+    }
+
+    /// Get how many decimal places the specified cryptocurrency's smallest unit is, if known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::Amount;
+    /// assert_eq!(Amount::currency_decimals("btc"), Some(8));
+    /// assert_eq!(Amount::currency_decimals("XMR"), Some(12));
+    /// assert_eq!(Amount::currency_decimals("zec"), None);
+    /// ```
+    pub fn currency_decimals(cryptocurrency: &str) -> Option<u32> {
+        let cryptocurrency = cryptocurrency.to_lowercase();
+        CURRENCY_DECIMALS.iter().find(|&&(c, _, _)| c == cryptocurrency).map(|&(_, decimals, _)| decimals)
+    }
+
+    /// Get the name of the specified cryptocurrency's smallest unit, if known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::Amount;
+    /// assert_eq!(Amount::currency_unit("xmr"), Some("piconero"));
+    /// assert_eq!(Amount::currency_unit("eth"), Some("wei"));
+    /// ```
+    pub fn currency_unit(cryptocurrency: &str) -> Option<&'static str> {
+        let cryptocurrency = cryptocurrency.to_lowercase();
+        CURRENCY_DECIMALS.iter().find(|&&(c, _, _)| c == cryptocurrency).map(|&(_, _, unit)| unit)
+    }
+
+    /// Get the amount as a whole number of smallest units.
+    pub fn atomic_units(&self) -> u128 {
+        self.atomic_units
+    }
+
+    /// Get how many decimal places the smallest unit is.
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+}
+
+impl fmt::Display for Amount {
+    /// Write the amount in plain decimal notation, without trailing zeroes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = format!("{:01$}", self.atomic_units, self.decimals as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - self.decimals as usize);
+        f.write_str(whole)?;
+
+        let fraction = fraction.trim_end_matches('0');
+        if !fraction.is_empty() {
+            write!(f, ".{}", fraction)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Smallest units, by cryptocurrency ticker.
+static CURRENCY_DECIMALS: &[(&str, u32, &str)] = &[("btc", 8, "satoshi"),
+                                                   ("ltc", 8, "litoshi"),
+                                                   ("doge", 8, "koinu"),
+                                                   ("xmr", 12, "piconero"),
+                                                   ("eth", 18, "wei")];
@@ -0,0 +1,428 @@
+//! Non-blocking counterparts of the lookup functions, available with the `async` feature.
+//!
+//! [`addresses_async()`](fn.addresses_async.html) and [`address_strings_async()`](fn.address_strings_async.html) ask the
+//! system's name servers on [tokio](https://tokio.rs)'s sockets, like [`DnsResolver`](struct.DnsResolver.html) would,
+//! so no thread is tied up while waiting for responses.
+//! [`addresses_with_config_async()`](fn.addresses_with_config_async.html) and
+//! [`address_strings_with_config_async()`](fn.address_strings_with_config_async.html) do the same with the specified
+//! [`ResolverConfig`](struct.ResolverConfig.html).
+//! If the system's configuration is needed, it's read on tokio's blocking thread pool when the future is first polled.
+//!
+//! [`Resolver`](trait.Resolver.html)s are blocking, so [`addresses_with_async()`](fn.addresses_with_async.html) and
+//! [`address_strings_with_async()`](fn.address_strings_with_async.html) merely offload them onto tokio's blocking thread
+//! pool, taking up one of its threads for each lookup in progress.
+//!
+//! Either way, the futures must be polled within a tokio runtime with both I/O and time enabled, e.g. one built with
+//! [`Builder::enable_all()`](https://docs.rs/tokio/1/tokio/runtime/struct.Builder.html#method.enable_all);
+//! outside of any runtime they resolve to an error.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate openalias;
+//! # extern crate tokio;
+//! # fn main() {
+//! let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+//! let addresses = runtime.block_on(openalias::addresses_async("donate@getmonero.org")).unwrap();
+//! for address in addresses {
+//!     println!("{}: {}", address.cryptocurrency, address.address);
+//! }
+//! # }
+//! ```
+//!
+//! Without a runtime:
+//!
+//! ```
+//! # use openalias::addresses_async;
+//! # use std::task::{Context, Poll, Waker};
+//! # use std::future::Future;
+//! let mut lookup = addresses_async("donate@getmonero.org");
+//! match std::pin::Pin::new(&mut lookup).poll(&mut Context::from_waker(Waker::noop())) {
+//!     Poll::Ready(Err(err)) => assert_eq!(err.to_string(), "Lookup futures must be polled within a tokio runtime"),
+//!     _ => panic!("looked up without a runtime"),
+//! }
+//! ```
+
+
+use self::super::{CryptoAddress, DnsResolver, ResolverConfig, Resolver, Transport, Lookup, Error, address_strings_with, addresses_with,
+                  alias_to_fqdn};
+use self::super::wire::{self, Exchange, Message, Rounds, Step, Upstream};
+use self::super::resolver::{Following, answered};
+use tokio::net::{TcpStream, UdpSocket};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{self, Instant, Sleep};
+use std::io::{self, ErrorKind};
+use tokio::runtime::Handle;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::future::{self, Future};
+use std::sync::Arc;
+use std::pin::Pin;
+use tokio::task;
+use std::net;
+
+
+/// A lookup in progress, resolves to what its blocking counterpart would've returned.
+pub struct LookupFuture<T> {
+    inner: Pin<Box<dyn Future<Output = Result<T, Error>> + Send>>,
+}
+
+impl<T> Future for LookupFuture<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, Error>> {
+        if Handle::try_current().is_err() {
+            return Poll::Ready(Err(Error::Io(io::Error::other("Lookup futures must be polled within a tokio runtime"))));
+        }
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+
+/// Asynchronously ask a DNS server for addresses for the specified OpenAlias.
+///
+/// The name servers are asked on tokio's sockets, see [the module docs](index.html) for the runtime needed.
+///
+/// See [`addresses()`](fn.addresses.html).
+pub fn addresses_async(address: &str) -> LookupFuture<Vec<CryptoAddress>> {
+    addresses_with_config_async(&ResolverConfig::new(), address)
+}
+
+/// Asynchronously ask a DNS server, as configured, for addresses for the specified OpenAlias.
+///
+/// The name servers are asked on tokio's sockets, see [the module docs](index.html) for the runtime needed.
+///
+/// See [`DnsResolver::with_config()`](struct.DnsResolver.html#method.with_config).
+pub fn addresses_with_config_async(config: &ResolverConfig, address: &str) -> LookupFuture<Vec<CryptoAddress>> {
+    look_up(config, address, |lookup| lookup.addresses())
+}
+
+/// Asynchronously ask the specified resolver for addresses for the specified OpenAlias.
+///
+/// The resolver is called on one of tokio's blocking threads, see [the module docs](index.html) for the runtime needed.
+///
+/// See [`addresses_with()`](fn.addresses_with.html).
+pub fn addresses_with_async<R: Resolver + Send + Sync + 'static>(resolver: Arc<R>, address: &str) -> LookupFuture<Vec<CryptoAddress>> {
+    let address = address.to_string();
+    spawn_blocking(move || addresses_with(&*resolver, &address))
+}
+
+/// Asynchronously ask a DNS server for "oa1:"-prefixed TXT records for the specified OpenAlias.
+///
+/// The name servers are asked on tokio's sockets, see [the module docs](index.html) for the runtime needed.
+///
+/// See [`address_strings()`](fn.address_strings.html).
+pub fn address_strings_async(address: &str) -> LookupFuture<Vec<String>> {
+    address_strings_with_config_async(&ResolverConfig::new(), address)
+}
+
+/// Asynchronously ask a DNS server, as configured, for "oa1:"-prefixed TXT records for the specified OpenAlias.
+///
+/// The name servers are asked on tokio's sockets, see [the module docs](index.html) for the runtime needed.
+///
+/// See [`DnsResolver::with_config()`](struct.DnsResolver.html#method.with_config).
+pub fn address_strings_with_config_async(config: &ResolverConfig, address: &str) -> LookupFuture<Vec<String>> {
+    look_up(config, address, |lookup| lookup.address_strings())
+}
+
+/// Asynchronously ask the specified resolver for "oa1:"-prefixed TXT records for the specified OpenAlias.
+///
+/// The resolver is called on one of tokio's blocking threads, see [the module docs](index.html) for the runtime needed.
+///
+/// See [`address_strings_with()`](fn.address_strings_with.html).
+pub fn address_strings_with_async<R: Resolver + Send + Sync + 'static>(resolver: Arc<R>, address: &str) -> LookupFuture<Vec<String>> {
+    let address = address.to_string();
+    spawn_blocking(move || address_strings_with(&*resolver, &address))
+}
+
+
+fn look_up<T: Send + 'static>(config: &ResolverConfig, address: &str, then: fn(Lookup) -> Result<T, Error>) -> LookupFuture<T> {
+    let fqdn = match alias_to_fqdn(address) {
+        Some(fqdn) => fqdn,
+        None => return ready(Err(Error::AddressParse)),
+    };
+
+    // Only the system's configuration needs reading, the rest is just checked
+    let mut resolver = if config.name_servers.is_empty() {
+        let config = config.clone();
+        spawn_blocking(move || DnsResolver::with_config(&config))
+    } else {
+        ready(DnsResolver::with_config(config))
+    };
+    let mut lookup: Option<TxtLookup<T>> = None;
+    LookupFuture {
+        inner: Box::pin(future::poll_fn(move |cx| loop {
+            if let Some(ref mut lookup) = lookup {
+                return Pin::new(lookup).poll(cx);
+            }
+            match Pin::new(&mut resolver).poll(cx) {
+                Poll::Ready(Ok(resolver)) => lookup = Some(TxtLookup::new(&resolver, &fqdn, then)),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        })),
+    }
+}
+
+fn ready<T: Send + 'static>(result: Result<T, Error>) -> LookupFuture<T> {
+    LookupFuture { inner: Box::pin(future::ready(result)) }
+}
+
+fn spawn_blocking<T, F>(f: F) -> LookupFuture<T>
+    where T: Send + 'static,
+          F: FnOnce() -> Result<T, Error> + Send + 'static
+{
+    let mut job = Some(f);
+    let mut handle = None;
+    LookupFuture {
+        inner: Box::pin(future::poll_fn(move |cx| {
+            let handle = handle.get_or_insert_with(|| task::spawn_blocking(job.take().expect("spawned once")));
+            match Pin::new(handle).poll(cx) {
+                Poll::Ready(Ok(result)) => Poll::Ready(result),
+                Poll::Ready(Err(err)) => Poll::Ready(Err(Error::Io(io::Error::other(err)))),
+                Poll::Pending => Poll::Pending,
+            }
+        })),
+    }
+}
+
+
+/// [`DnsResolver::lookup()`](struct.DnsResolver.html#method.lookup) on tokio's sockets, then `then` on the result.
+struct TxtLookup<T> {
+    fqdn: String,
+    upstream: Upstream,
+    edns: Option<(u16, bool)>,
+    following: Following,
+    rounds: Rounds,
+    exchange: Option<SocketExchange>,
+    answered_by: Option<(SocketAddr, Transport)>,
+    then: fn(Lookup) -> Result<T, Error>,
+}
+
+impl<T> TxtLookup<T> {
+    fn new(resolver: &DnsResolver, fqdn: &str, then: fn(Lookup) -> Result<T, Error>) -> TxtLookup<T> {
+        TxtLookup {
+            fqdn: fqdn.to_string(),
+            upstream: resolver.upstream(),
+            edns: resolver.edns(),
+            following: Following::new(fqdn),
+            rounds: Rounds::new(),
+            exchange: None,
+            answered_by: None,
+            then,
+        }
+    }
+}
+
+impl<T> Future for TxtLookup<T> {
+    type Output = Result<T, Error>;
+
+    /// Ask the servers in turn, like `Upstream::query_existing()`, for each name `Following` needs.
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, Error>> {
+        let this = &mut *self;
+        loop {
+            let (server, outcome) = match this.exchange {
+                None => {
+                    let server = match this.rounds.next_server(&this.upstream) {
+                        Ok(server) => server,
+                        Err(err) => return Poll::Ready(Err(err)),
+                    };
+                    match SocketExchange::new(server, this.following.name(), this.edns, this.upstream.timeout) {
+                        Ok(exchange) => {
+                            this.exchange = Some(exchange);
+                            continue;
+                        }
+                        Err(err) => (server, Err(err)),
+                    }
+                }
+                Some(ref mut exchange) => {
+                    match exchange.poll(cx) {
+                        Poll::Ready(outcome) => (exchange.server, outcome),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+            };
+            this.exchange = None;
+
+            let (transport, response) = match this.rounds.outcome(server, outcome) {
+                Ok(Some(response)) => response,
+                Ok(None) => continue,
+                Err(err) => return Poll::Ready(Err(err)),
+            };
+            if let Err(err) = wire::check_existing(this.following.name(), &response) {
+                return Poll::Ready(Err(err));
+            }
+            this.answered_by = Some((server, transport));
+            this.rounds = Rounds::new();
+
+            match this.following.feed(&response) {
+                Ok(Some(lookup)) => return Poll::Ready(answered(&this.fqdn, lookup, this.answered_by).and_then(this.then)),
+                Ok(None) => {}
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+}
+
+
+/// Asking a single server on tokio's sockets, as the `wire::Exchange` directs.
+struct SocketExchange {
+    server: SocketAddr,
+    timeout: Duration,
+    exchange: Exchange,
+    socket: UdpSocket,
+    sent: bool,
+    buf: Vec<u8>,
+    tcp: Option<TcpExchange>,
+    /// When the current response stops being waited for.
+    deadline: Pin<Box<Sleep>>,
+}
+
+impl SocketExchange {
+    fn new(server: SocketAddr, name: &str, edns: Option<(u16, bool)>, timeout: Duration) -> Result<SocketExchange, Error> {
+        let exchange = Exchange::new(name, wire::TYPE_TXT, wire::FLAG_RD | wire::FLAG_AD, edns)?;
+        let socket = net::UdpSocket::bind(if server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+            .and_then(UdpSocket::from_std)
+            .map_err(wire::io_error)?;
+        Ok(SocketExchange {
+            server,
+            timeout,
+            exchange,
+            socket,
+            sent: false,
+            buf: vec![0; 65535],
+            tcp: None,
+            deadline: Box::pin(time::sleep(timeout)),
+        })
+    }
+
+    fn poll(&mut self, cx: &mut Context) -> Poll<Result<(Transport, Message), Error>> {
+        loop {
+            if let Some(ref mut tcp) = self.tcp {
+                return match tcp.poll(cx) {
+                    Poll::Ready(Ok(response)) => Poll::Ready(Ok((Transport::Tcp, Message::parse(&response)?))),
+                    Poll::Ready(Err(err)) => Poll::Ready(Err(wire::io_error(err))),
+                    Poll::Pending => SocketExchange::poll_deadline(&mut self.deadline, cx),
+                };
+            }
+
+            let step = match self.poll_udp(cx) {
+                Poll::Ready(Ok(())) => self.exchange.udp_response(&self.buf)?,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(wire::io_error(err))),
+                Poll::Pending => return SocketExchange::poll_deadline(&mut self.deadline, cx),
+            };
+            match step {
+                Step::Done(response) => return Poll::Ready(Ok((Transport::Udp, response))),
+                Step::Retry => self.sent = false,
+                Step::Tcp => self.tcp = Some(TcpExchange::new(self.server, self.exchange.query())),
+            }
+            self.deadline.as_mut().reset(Instant::now() + self.timeout);
+        }
+    }
+
+    /// Send the query, and wait for a response to it, leaving it in `buf`.
+    fn poll_udp(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        if !self.sent {
+            match self.socket.poll_send_to(cx, self.exchange.query(), self.server) {
+                Poll::Ready(Ok(_)) => self.sent = true,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        loop {
+            self.buf.resize(65535, 0);
+            let mut buf = ReadBuf::new(&mut self.buf);
+            let from = match self.socket.poll_recv_from(cx, &mut buf) {
+                Poll::Ready(Ok(from)) => from,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            };
+            let len = buf.filled().len();
+            self.buf.truncate(len);
+            if from == self.server && wire::answers_query(self.exchange.query(), &self.buf) {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+
+    fn poll_deadline<R>(deadline: &mut Pin<Box<Sleep>>, cx: &mut Context) -> Poll<Result<R, Error>> {
+        match deadline.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Error::Timeout)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+
+/// Sending a length-prefixed query over a fresh TCP connection, and reading the response, which must be to it,
+/// as in `exchange_stream()`.
+struct TcpExchange {
+    connect: Option<Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>>,
+    stream: Option<TcpStream>,
+    framed: Vec<u8>,
+    written: usize,
+    /// The length prefix, then the response once that's known.
+    response: Vec<u8>,
+    read: usize,
+}
+
+impl TcpExchange {
+    fn new(server: SocketAddr, query: &[u8]) -> TcpExchange {
+        TcpExchange {
+            connect: Some(Box::pin(TcpStream::connect(server))),
+            stream: None,
+            framed: wire::frame(query),
+            written: 0,
+            response: vec![0; 2],
+            read: 0,
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context) -> Poll<io::Result<Vec<u8>>> {
+        if let Some(mut connect) = self.connect.take() {
+            match connect.as_mut().poll(cx) {
+                Poll::Ready(Ok(stream)) => self.stream = Some(stream),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => {
+                    self.connect = Some(connect);
+                    return Poll::Pending;
+                }
+            }
+        }
+        let stream = self.stream.as_mut().expect("connected above");
+
+        while self.written < self.framed.len() {
+            match Pin::new(&mut *stream).poll_write(cx, &self.framed[self.written..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(written)) => self.written += written,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        while self.read < self.response.len() {
+            let mut buf = ReadBuf::new(&mut self.response[self.read..]);
+            let read = match Pin::new(&mut *stream).poll_read(cx, &mut buf) {
+                Poll::Ready(Ok(())) => buf.filled().len(),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            };
+            if read == 0 {
+                return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()));
+            }
+            self.read += read;
+
+            if self.read == 2 && self.response.len() == 2 {
+                let len = u16::from_be_bytes([self.response[0], self.response[1]]) as usize;
+                self.response.resize(2 + len, 0);
+            }
+        }
+
+        let response = self.response.split_off(2);
+        wire::expect_answer(&self.framed[2..], &response)?;
+        Poll::Ready(Ok(response))
+    }
+}
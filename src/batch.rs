@@ -0,0 +1,166 @@
+use self::super::{CryptoAddress, DnsResolver, ResolverConfig, Resolver, Lookup, Error, address_strings_with, addresses_with, lookup_with};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+
+/// Ask DNS servers for addresses for all the specified OpenAliases, looking up at most `parallelism` of them at once.
+///
+/// Each worker thread gets its own [`DnsResolver`](struct.DnsResolver.html) with the specified settings.
+///
+/// The results are in the same order as the aliases.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{ResolverConfig, batch_addresses};
+/// let aliases = ["donate@getmonero.org", "nabijaczleweli.xyz"];
+/// for (alias, result) in aliases.iter().zip(batch_addresses(&aliases, &ResolverConfig::new(), 8)) {
+///     match result {
+///         Ok(addresses) => println!("{}: {} addresses", alias, addresses.len()),
+///         Err(err) => println!("{}: {}", alias, err),
+///     }
+/// }
+/// ```
+pub fn batch_addresses<S: AsRef<str> + Sync>(aliases: &[S], config: &ResolverConfig, parallelism: usize) -> Vec<Result<Vec<CryptoAddress>, Error>> {
+    run(aliases, parallelism, || {
+        let mut resolver = None;
+        move |alias: &str| {
+            if resolver.is_none() {
+                resolver = Some(DnsResolver::with_config(config)?);
+            }
+            addresses_with(resolver.as_ref().unwrap(), alias)
+        }
+    })
+}
+
+/// Ask the specified resolver for addresses for all the specified OpenAliases, looking up at most `parallelism` of them
+/// at once.
+///
+/// The results are in the same order as the aliases.
+pub fn batch_addresses_with<R, S>(resolver: &R, aliases: &[S], parallelism: usize) -> Vec<Result<Vec<CryptoAddress>, Error>>
+    where R: Resolver + Sync + ?Sized,
+          S: AsRef<str> + Sync
+{
+    run(aliases, parallelism, || move |alias: &str| addresses_with(resolver, alias))
+}
+
+/// Ask DNS servers for "oa1:"-prefixed TXT records for all the specified OpenAliases, looking up at most `parallelism` of
+/// them at once.
+///
+/// Each worker thread gets its own [`DnsResolver`](struct.DnsResolver.html) with the specified settings.
+///
+/// The results are in the same order as the aliases.
+pub fn batch_address_strings<S: AsRef<str> + Sync>(aliases: &[S], config: &ResolverConfig, parallelism: usize) -> Vec<Result<Vec<String>, Error>> {
+    run(aliases, parallelism, || {
+        let mut resolver = None;
+        move |alias: &str| {
+            if resolver.is_none() {
+                resolver = Some(DnsResolver::with_config(config)?);
+            }
+            address_strings_with(resolver.as_ref().unwrap(), alias)
+        }
+    })
+}
+
+/// Ask the specified resolver for "oa1:"-prefixed TXT records for all the specified OpenAliases, looking up at most
+/// `parallelism` of them at once.
+///
+/// The results are in the same order as the aliases.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{Error, batch_address_strings_with};
+/// let fake = |fqdn: &str| -> Result<Vec<Vec<u8>>, Error> {
+///     Ok(vec![format!("oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H; recipient_name={}", fqdn).into_bytes()])
+/// };
+///
+/// let aliases: Vec<_> = (0..100).map(|i| format!("donate{}@example.com", i)).collect();
+/// let results = batch_address_strings_with(&fake, &aliases, 16);
+/// assert_eq!(results.len(), 100);
+/// for (i, result) in results.into_iter().enumerate() {
+///     assert!(result.unwrap()[0].ends_with(&format!("recipient_name=donate{}.example.com.", i)));
+/// }
+/// ```
+pub fn batch_address_strings_with<R, S>(resolver: &R, aliases: &[S], parallelism: usize) -> Vec<Result<Vec<String>, Error>>
+    where R: Resolver + Sync + ?Sized,
+          S: AsRef<str> + Sync
+{
+    run(aliases, parallelism, || move |alias: &str| address_strings_with(resolver, alias))
+}
+
+/// Ask DNS servers for all TXT records for all the specified OpenAliases, alongside whatever is known about the lookups,
+/// looking up at most `parallelism` of them at once.
+///
+/// Each worker thread gets its own [`DnsResolver`](struct.DnsResolver.html) with the specified settings.
+///
+/// The results are in the same order as the aliases.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{ResolverConfig, batch_lookups};
+/// let aliases = ["donate@getmonero.org", "nabijaczleweli.xyz"];
+/// for result in batch_lookups(&aliases, &ResolverConfig::new(), 8) {
+///     if let Ok(lookup) = result {
+///         println!("{}: {} records, via {:?}", lookup.fqdn, lookup.records.len(), lookup.cname_chain);
+///     }
+/// }
+/// ```
+pub fn batch_lookups<S: AsRef<str> + Sync>(aliases: &[S], config: &ResolverConfig, parallelism: usize) -> Vec<Result<Lookup, Error>> {
+    run(aliases, parallelism, || {
+        let mut resolver = None;
+        move |alias: &str| {
+            if resolver.is_none() {
+                resolver = Some(DnsResolver::with_config(config)?);
+            }
+            lookup_with(resolver.as_ref().unwrap(), alias)
+        }
+    })
+}
+
+/// Ask the specified resolver for all TXT records for all the specified OpenAliases, alongside whatever is known about
+/// the lookups, looking up at most `parallelism` of them at once.
+///
+/// The results are in the same order as the aliases.
+pub fn batch_lookups_with<R, S>(resolver: &R, aliases: &[S], parallelism: usize) -> Vec<Result<Lookup, Error>>
+    where R: Resolver + Sync + ?Sized,
+          S: AsRef<str> + Sync
+{
+    run(aliases, parallelism, || move |alias: &str| lookup_with(resolver, alias))
+}
+
+
+/// Spin up to `parallelism` workers (each with a lookup function from `make_worker`), which take aliases in order until
+/// there are none left.
+fn run<S, T, W, L>(aliases: &[S], parallelism: usize, make_worker: W) -> Vec<Result<T, Error>>
+    where S: AsRef<str> + Sync,
+          T: Send,
+          W: Fn() -> L + Sync,
+          L: FnMut(&str) -> Result<T, Error>
+{
+    let next = AtomicUsize::new(0);
+    let workers = parallelism.max(1).min(aliases.len());
+
+    let mut results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut lookup = make_worker();
+                    let mut done = vec![];
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        match aliases.get(idx) {
+                            Some(alias) => done.push((idx, lookup(alias.as_ref()))),
+                            None => break done,
+                        }
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().expect("lookup thread panicked")).collect()
+    });
+
+    results.sort_by_key(|&(idx, _)| idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
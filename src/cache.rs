@@ -0,0 +1,275 @@
+use self::super::{Resolver, Lookup, Error};
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+
+/// A resolver remembering another resolver's answers for as long as their TTLs allow.
+///
+/// The lifetime of an answer is the lowest TTL among its records and the CNAMEs and DNAMEs leading to them,
+/// clamped to `[min_ttl, max_ttl]` (answers of unknown TTL get `default_ttl`);
+/// empty answers and nonexistent domains are remembered for `negative_ttl`.
+///
+/// At most `max_entries` answers are remembered: expired ones are forgotten first, then the ones expiring soonest.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{CachingResolver, Error, addresses_with};
+/// # use std::time::{Duration, Instant};
+/// # use std::cell::Cell;
+/// let queries = Cell::new(0);
+/// let upstream = |_: &str| -> Result<Vec<Vec<u8>>, Error> {
+///     queries.set(queries.get() + 1);
+///     Ok(vec![b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;".to_vec()])
+/// };
+///
+/// // This resolver doesn't know TTLs, so default_ttl is used
+/// let cache = CachingResolver::new(upstream).default_ttl(Duration::from_secs(60));
+/// for _ in 0..10 {
+///     addresses_with(&cache, "donate@example.com").unwrap();
+/// }
+/// assert_eq!(queries.get(), 1);
+///
+/// let entries = cache.entries();
+/// assert_eq!(entries.len(), 1);
+/// assert_eq!(entries[0].fqdn, "donate.example.com.");
+/// assert!(entries[0].expires <= Instant::now() + Duration::from_secs(60));
+///
+/// cache.clear();
+/// addresses_with(&cache, "donate@example.com").unwrap();
+/// assert_eq!(queries.get(), 2);
+///
+/// // Making room forgets the answers expiring soonest
+/// let cache = CachingResolver::new(upstream).max_entries(1);
+/// addresses_with(&cache, "donate@example.com").unwrap();
+/// addresses_with(&cache, "donate@example.net").unwrap();
+/// assert_eq!(cache.entries().len(), 1);
+/// ```
+///
+/// The CNAMEs followed count, too:
+///
+/// ```
+/// # use openalias::{CachingResolver, Error, Lookup, Resolver, TxtRecord};
+/// # use std::time::{Duration, Instant};
+/// struct Upstream;
+/// impl Resolver for Upstream {
+///     fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+///         Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+///     }
+///
+///     fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+///         let record = TxtRecord::from_segments(&[&b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;"[..]], Some(3600));
+///         let mut lookup = Lookup::new(fqdn.to_string(), vec![record]);
+///         lookup.cname_chain = vec!["donate.hostingco.net.".to_string()];
+///         lookup.cname_ttl = Some(60);
+///         Ok(lookup)
+///     }
+/// }
+///
+/// let cache = CachingResolver::new(Upstream);
+/// cache.lookup("donate.example.com.").unwrap();
+/// assert!(cache.entries()[0].expires <= Instant::now() + Duration::from_secs(60));
+/// ```
+#[derive(Debug)]
+pub struct CachingResolver<R: Resolver> {
+    resolver: R,
+    entries: Mutex<HashMap<String, (Answer, Instant)>>,
+    min_ttl: Duration,
+    max_ttl: Duration,
+    default_ttl: Duration,
+    negative_ttl: Duration,
+    max_entries: usize,
+}
+
+/// A remembered answer.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// The FQDN the answer is for.
+    pub fqdn: String,
+    /// The answer, or `None` if the domain doesn't exist or has no TXT records.
+    pub lookup: Option<Lookup>,
+    /// When the answer will be forgotten.
+    pub expires: Instant,
+}
+
+impl<R: Resolver> CachingResolver<R> {
+    /// Remember answers from the specified resolver.
+    ///
+    /// Default TTL bounds: `[0s, 1 day]`, default TTL for answers without one: 5 minutes,
+    /// default negative TTL: 60 seconds, default size: 1024 answers.
+    pub fn new(resolver: R) -> CachingResolver<R> {
+        CachingResolver {
+            resolver,
+            entries: Mutex::new(HashMap::new()),
+            min_ttl: Duration::from_secs(0),
+            max_ttl: Duration::from_secs(24 * 60 * 60),
+            default_ttl: Duration::from_secs(5 * 60),
+            negative_ttl: Duration::from_secs(60),
+            max_entries: 1024,
+        }
+    }
+
+    /// Remember answers for at least this long, regardless of their TTLs.
+    pub fn min_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.min_ttl = ttl;
+        self
+    }
+
+    /// Remember answers for at most this long, regardless of their TTLs.
+    pub fn max_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.max_ttl = ttl;
+        self
+    }
+
+    /// Remember answers whose TTLs aren't known for this long, within `[min_ttl, max_ttl]`.
+    pub fn default_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Remember empty answers and nonexistent domains for this long.
+    pub fn negative_ttl(mut self, ttl: Duration) -> CachingResolver<R> {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Remember at most this many answers.
+    ///
+    /// Zero disables caching altogether.
+    pub fn max_entries(mut self, max_entries: usize) -> CachingResolver<R> {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Get the wrapped resolver.
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Get all answers that haven't expired yet.
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|&(_, &(_, expires))| expires > now)
+            .map(|(fqdn, &(ref answer, expires))| {
+                CacheEntry {
+                    fqdn: fqdn.clone(),
+                    lookup: match *answer {
+                        Answer::Found(ref lookup) => Some(lookup.clone()),
+                        Answer::NxDomain | Answer::NoTxtData => None,
+                    },
+                    expires,
+                }
+            })
+            .collect()
+    }
+
+    /// Forget the answer for the specified FQDN, returning whether there was one.
+    pub fn remove(&self, fqdn: &str) -> bool {
+        self.entries.lock().unwrap().remove(&fqdn.to_lowercase()).is_some()
+    }
+
+    /// Forget all answers.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn cached(&self, fqdn: &str) -> Option<Result<Lookup, Error>> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+
+        let expires = entries.get(fqdn)?.1;
+        if expires <= now {
+            entries.remove(fqdn);
+            return None;
+        }
+
+        Some(match entries[fqdn].0 {
+            Answer::Found(ref lookup) => {
+                let mut lookup = lookup.clone();
+                let remaining = (expires - now).as_secs() as u32;
+                for record in &mut lookup.records {
+                    record.ttl = record.ttl.map(|ttl| ttl.min(remaining));
+                }
+                lookup.cname_ttl = lookup.cname_ttl.map(|ttl| ttl.min(remaining));
+                Ok(lookup)
+            }
+            Answer::NxDomain => Err(Error::NxDomain(fqdn.to_string())),
+            Answer::NoTxtData => Err(Error::NoTxtData(fqdn.to_string())),
+        })
+    }
+
+    fn lifetime(&self, lookup: &Lookup) -> Duration {
+        if lookup.records.is_empty() {
+            return self.negative_ttl;
+        }
+
+        let ttl = lookup.records
+            .iter()
+            .filter_map(|r| r.ttl)
+            .chain(lookup.cname_ttl)
+            .min()
+            .map(|ttl| Duration::from_secs(ttl as u64))
+            .unwrap_or(self.default_ttl);
+        ttl.max(self.min_ttl).min(self.max_ttl)
+    }
+
+    /// Remember the answer, making room for it if need be.
+    fn insert(&self, fqdn: String, answer: Answer, lifetime: Duration) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        if entries.len() >= self.max_entries && !entries.contains_key(&fqdn) {
+            entries.retain(|_, &mut (_, expires)| expires > now);
+        }
+        while entries.len() >= self.max_entries && !entries.contains_key(&fqdn) {
+            let soonest = entries.iter().min_by_key(|&(_, &(_, expires))| expires).map(|(fqdn, _)| fqdn.clone()).unwrap();
+            entries.remove(&soonest);
+        }
+        entries.insert(fqdn, (answer, now + lifetime));
+    }
+}
+
+impl<R: Resolver> Resolver for CachingResolver<R> {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+    }
+
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        let key = fqdn.to_lowercase();
+        if let Some(cached) = self.cached(&key) {
+            return cached;
+        }
+
+        match self.resolver.lookup(fqdn) {
+            Ok(lookup) => {
+                self.insert(key, Answer::Found(lookup.clone()), self.lifetime(&lookup));
+                Ok(lookup)
+            }
+            Err(err) => {
+                let answer = match err {
+                    Error::NxDomain(_) => Answer::NxDomain,
+                    Error::NoTxtData(_) => Answer::NoTxtData,
+                    _ => return Err(err),
+                };
+                self.insert(key, answer, self.negative_ttl);
+                Err(err)
+            }
+        }
+    }
+}
+
+
+#[derive(Debug)]
+enum Answer {
+    Found(Lookup),
+    NxDomain,
+    NoTxtData,
+}
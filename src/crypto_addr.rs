@@ -1,4 +1,6 @@
-use self::super::{grammar, ParseError};
+use self::super::{grammar, MoneroAddress, SignatureValidity, ParseError, Amount, Error, alias_to_fqdn};
+use self::super::monero::parse_payment_id;
+use self::super::signature;
 use crc::crc32::{self, Hasher32};
 use std::collections::BTreeMap;
 use std::{str, fmt, mem};
@@ -177,6 +179,182 @@ pub struct CryptoAddress {
     pub additional_values: BTreeMap<String, String>,
 }
 
+impl CryptoAddress {
+    /// Decode the address, if this is an "xmr" record, in any case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::{CryptoAddress, MoneroAddressType};
+    /// let record: CryptoAddress = "oa1:xmr recipient_address=46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em;"
+    ///     .parse().unwrap();
+    /// assert_eq!(record.monero_address().unwrap().unwrap().address_type, MoneroAddressType::Standard);
+    /// let mut record = record;
+    /// record.cryptocurrency = "XMR".to_string();
+    /// assert!(record.monero_address().unwrap().is_some());
+    ///
+    /// let record: CryptoAddress = "oa1:btc recipient_address=1MoSyGZp3SKpoiXPXfZDFK7cDUFCVtEDeS;".parse().unwrap();
+    /// assert!(record.monero_address().unwrap().is_none());
+    /// ```
+    pub fn monero_address(&self) -> Result<Option<MoneroAddress>, Error> {
+        if !self.cryptocurrency.eq_ignore_ascii_case("xmr") {
+            return Ok(None);
+        }
+        self.address.parse().map(Some)
+    }
+
+    /// Get the integrated address to pay, if this is an "xmr" record with either an integrated address, or a standard
+    /// address and a short (16 hex digit) `tx_payment_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::{CryptoAddress, MoneroAddressType};
+    /// let record: CryptoAddress = "oa1:xmr recipient_address=46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em; \
+    ///                              tx_payment_id=123456789abcdef0;".parse().unwrap();
+    /// let integrated = record.integrated_address().unwrap().unwrap();
+    /// assert_eq!(integrated.address_type, MoneroAddressType::Integrated);
+    /// assert_eq!(integrated.payment_id, Some([0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0]));
+    /// assert_eq!(integrated.standard().to_string(), record.address);
+    ///
+    /// let record: CryptoAddress = "oa1:xmr recipient_address=46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em;"
+    ///     .parse().unwrap();
+    /// assert!(record.integrated_address().unwrap().is_none());
+    /// ```
+    pub fn integrated_address(&self) -> Result<Option<MoneroAddress>, Error> {
+        let address = match self.monero_address()? {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+        if address.payment_id.is_some() {
+            return Ok(Some(address));
+        }
+
+        match self.tx_payment_id.as_ref().and_then(|id| parse_payment_id(id)) {
+            Some(payment_id) => address.integrated(payment_id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse `tx_amount`, if any, as an exact amount of the record's cryptocurrency, which must have a known smallest unit.
+    ///
+    /// See [`Amount`](struct.Amount.html) for the accepted format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::CryptoAddress;
+    /// let record: CryptoAddress = "oa1:xmr recipient_address=46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em; \
+    ///                              tx_amount=0.25;".parse().unwrap();
+    /// assert_eq!(record.amount().unwrap().unwrap().atomic_units(), 250_000_000_000);
+    ///
+    /// let record: CryptoAddress = "oa1:btc recipient_address=1MoSyGZp3SKpoiXPXfZDFK7cDUFCVtEDeS; tx_amount=1e-3;".parse().unwrap();
+    /// assert!(record.amount().is_err());
+    ///
+    /// let record: CryptoAddress = "oa1:btc recipient_address=1MoSyGZp3SKpoiXPXfZDFK7cDUFCVtEDeS;".parse().unwrap();
+    /// assert_eq!(record.amount().unwrap(), None);
+    /// ```
+    pub fn amount(&self) -> Result<Option<Amount>, Error> {
+        match self.tx_amount.as_ref() {
+            Some(tx_amount) => Amount::parse_for(tx_amount, &self.cryptocurrency).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Check whether `address_signature` signs the alias' lowercased FQDN (without the final dot) with the recipient address'
+    /// key.
+    ///
+    /// For "btc" records (in any case), this is a Bitcoin signed message by a P2PKH, P2SH-P2WPKH, or P2WPKH (bc1 or tb1) address,
+    /// and for "xmr" records, a Monero "SigV1" or "SigV2" message signature by the spend key;
+    /// other records are unsupported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::{CryptoAddress, SignatureValidity};
+    /// let record: CryptoAddress = "oa1:btc recipient_address=1J3USHyUpZmfNkrjLVQTiGDsU58hihgQZW; \
+    ///                              address_signature=IGEE4mO5AkywGbj+Y45UHhMA/dq7t54n5UlXWYRcdComAes1Mvel748QtkVpHzVOBO9fRfTk7nqWvqQO3K+VaEY=;"
+    ///     .parse().unwrap();
+    /// assert_eq!(record.verify_signature("donate@example.com"), SignatureValidity::Valid);
+    /// assert_eq!(record.verify_signature("donate.example.com."), SignatureValidity::Valid);
+    /// assert_eq!(record.verify_signature("donate@example.org"),
+    ///            SignatureValidity::Invalid("signed by another key".to_string()));
+    ///
+    /// // Same key, as SegWit
+    /// let record: CryptoAddress = "oa1:btc recipient_address=bc1qhtca76up7539rxe0339u9k6ws43qsehvqh56r7; \
+    ///                              address_signature=IGEE4mO5AkywGbj+Y45UHhMA/dq7t54n5UlXWYRcdComAes1Mvel748QtkVpHzVOBO9fRfTk7nqWvqQO3K+VaEY=;"
+    ///     .parse().unwrap();
+    /// assert!(record.verify_signature("donate@example.com").is_valid());
+    /// let record: CryptoAddress = "oa1:btc recipient_address=tb1qhtca76up7539rxe0339u9k6ws43qsehv230fcd; \
+    ///                              address_signature=IGEE4mO5AkywGbj+Y45UHhMA/dq7t54n5UlXWYRcdComAes1Mvel748QtkVpHzVOBO9fRfTk7nqWvqQO3K+VaEY=;"
+    ///     .parse().unwrap();
+    /// assert!(record.verify_signature("donate@example.com").is_valid());
+    /// let record: CryptoAddress = "oa1:btc recipient_address=3GuoTV86615C4xPKeSmPATeJbSDUYVNXxf; \
+    ///                              address_signature=IGEE4mO5AkywGbj+Y45UHhMA/dq7t54n5UlXWYRcdComAes1Mvel748QtkVpHzVOBO9fRfTk7nqWvqQO3K+VaEY=;"
+    ///     .parse().unwrap();
+    /// assert!(record.verify_signature("donate@example.com").is_valid());
+    ///
+    /// let mut record = record;
+    /// record.cryptocurrency = "BTC".to_string();
+    /// assert!(record.verify_signature("donate@example.com").is_valid());
+    ///
+    /// // The alias is compared case-insensitively, like DNS names are
+    /// assert!(record.verify_signature("Donate@Example.COM").is_valid());
+    ///
+    /// // Same key, as a Litecoin address
+    /// let record: CryptoAddress = "oa1:btc recipient_address=ltc1qhtca76up7539rxe0339u9k6ws43qsehvytw7mw; \
+    ///                              address_signature=IGEE4mO5AkywGbj+Y45UHhMA/dq7t54n5UlXWYRcdComAes1Mvel748QtkVpHzVOBO9fRfTk7nqWvqQO3K+VaEY=;"
+    ///     .parse().unwrap();
+    /// assert_eq!(record.verify_signature("donate@example.com"), SignatureValidity::Unsupported);
+    ///
+    /// // Uncompressed key
+    /// let record: CryptoAddress = "oa1:btc recipient_address=1BfyB6qLpYfiG52J5CnCbUkQTRb6YBBZ9s; \
+    ///                              address_signature=HGEE4mO5AkywGbj+Y45UHhMA/dq7t54n5UlXWYRcdComAes1Mvel748QtkVpHzVOBO9fRfTk7nqWvqQO3K+VaEY=;"
+    ///     .parse().unwrap();
+    /// assert!(record.verify_signature("donate@example.com").is_valid());
+    ///
+    /// let record: CryptoAddress = "oa1:btc recipient_address=1MoSyGZp3SKpoiXPXfZDFK7cDUFCVtEDeS;".parse().unwrap();
+    /// assert_eq!(record.verify_signature("donate@example.com"), SignatureValidity::Unsupported);
+    ///
+    /// let record: CryptoAddress = "oa1:xmr recipient_address=438woJChyn7DNbsS188drACg87HJNRpxwJjPAZqJtSh9iRWc3bgjgNPXT6qVFcevAdBQ6pFRRjXTXAeM4X11okyfVNSFVPR; \
+    ///                              address_signature=SigV2KRgDzmgUyeQDBaXPQBGdMdJ9UiA2kRNaCg27tate8XN471ZX1DHrVVt1oSVVSBMhBZXbdRDyetDDpZSBxZT4hmfJ;"
+    ///     .parse().unwrap();
+    /// assert!(record.verify_signature("donate@example.com").is_valid());
+    /// assert!(!record.verify_signature("donate@example.org").is_valid());
+    /// let record: CryptoAddress = "oa1:xmr recipient_address=438woJChyn7DNbsS188drACg87HJNRpxwJjPAZqJtSh9iRWc3bgjgNPXT6qVFcevAdBQ6pFRRjXTXAeM4X11okyfVNSFVPR; \
+    ///                              address_signature=SigV1EiygVr2ZdYw2nMPCiZbFHP85BmsRwXTZrBJZsfZE7zkECCsqLvHyr4PZx58GV65YUBda1vJJSZ3gEBppFALm99ph;"
+    ///     .parse().unwrap();
+    /// assert!(record.verify_signature("donate@example.com").is_valid());
+    ///
+    /// // Typo'd address
+    /// let record: CryptoAddress = "oa1:xmr recipient_address=438woJChyn7DNbsS188drACg87HJNRpxwJjPAZqJtSh9iRWc3bgjgNPXT6qVFcevAdBQ6pFRRjXTXAeM4X11okyfVNSFVPr; \
+    ///                              address_signature=SigV1EiygVr2ZdYw2nMPCiZbFHP85BmsRwXTZrBJZsfZE7zkECCsqLvHyr4PZx58GV65YUBda1vJJSZ3gEBppFALm99ph;"
+    ///     .parse().unwrap();
+    /// assert_eq!(record.verify_signature("donate@example.com"),
+    ///            SignatureValidity::Invalid("no spend key to check against: Malformed Monero address: checksum mismatch".to_string()));
+    /// ```
+    pub fn verify_signature(&self, alias: &str) -> SignatureValidity {
+        let address_signature = match self.address_signature.as_ref() {
+            Some(address_signature) => address_signature,
+            None => return SignatureValidity::Unsupported,
+        };
+        let fqdn = alias_to_fqdn(alias).unwrap_or_else(|| alias.to_string()).to_lowercase();
+        let message = fqdn.trim_end_matches('.');
+
+        if self.cryptocurrency.eq_ignore_ascii_case("btc") {
+            signature::bitcoin(&self.address, address_signature, message)
+        } else if self.cryptocurrency.eq_ignore_ascii_case("xmr") {
+            match self.monero_address() {
+                Ok(Some(address)) => signature::monero(&address, address_signature, message),
+                Ok(None) => unreachable!("an xmr record"),
+                Err(err) => SignatureValidity::Invalid(format!("no spend key to check against: {}", err)),
+            }
+        } else {
+            SignatureValidity::Unsupported
+        }
+    }
+}
+
 impl str::FromStr for CryptoAddress {
     type Err = ParseError;
 
@@ -0,0 +1,835 @@
+//! Validating [DNSSEC](https://tools.ietf.org/html/rfc4033) lookups.
+//!
+//! Quoting [OpenAlias](https://openalias.org#implement):
+//!
+//! > It is important that you check the DNSSEC validation status of the lookup, and either refuse to continue
+//! > or warn the user if the DNSSEC validation failed.
+//!
+//! The name servers are only relied on to deliver the records, signatures and keys (the CD bit is set on queries),
+//! the chain RRSIG -> DNSKEY -> DS -> … is checked locally, down from one of the configured trust anchors.
+//!
+//! Records missing signatures in a signed zone are bogus: they're only insecure below a delegation which the zone above
+//! proves, with NSEC or NSEC3 records, to have no DS records.
+
+
+use self::super::{CryptoAddress, ResolverConfig, Resolver, Transport, Lookup, Error, alias_to_fqdn};
+use self::super::wire::{self, Message, Record, Upstream};
+use self::super::resolver::Following;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::cmp::Ordering;
+use ring::digest;
+use std::fmt;
+
+
+/// Most NSEC3 hash iterations done, as recommended in RFC 9276; records with more aren't taken as proof of anything.
+const MAX_NSEC3_ITERATIONS: u16 = 150;
+
+
+/// Result of authenticating a record.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DnssecStatus {
+    /// The record's signature chains up to a trust anchor.
+    Secure,
+    /// The record could not be authenticated, because it's below a delegation proven to be unsigned,
+    /// or not under any trust anchor.
+    Insecure,
+    /// The record should chain up to a trust anchor, but doesn't, with the reason why:
+    /// its signatures, or ones on the way up, are missing, expired, or don't check out.
+    ///
+    /// This is what tampering with a response about a signed zone looks like. Records from unsigned zones are `Insecure`,
+    /// and can't be told apart from tampered-with ones.
+    Bogus(String),
+}
+
+impl DnssecStatus {
+    /// Check whether the record was authenticated.
+    pub fn is_secure(&self) -> bool {
+        *self == DnssecStatus::Secure
+    }
+
+    /// Combine the statuses of records relied on together, keeping the first reason for being bogus.
+    fn worst(self, other: DnssecStatus) -> DnssecStatus {
+        match self {
+            DnssecStatus::Bogus(_) => self,
+            _ => self.max(other),
+        }
+    }
+}
+
+impl fmt::Display for DnssecStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DnssecStatus::Secure => f.write_str("secure"),
+            DnssecStatus::Insecure => f.write_str("insecure"),
+            DnssecStatus::Bogus(ref why) => write!(f, "bogus ({})", why),
+        }
+    }
+}
+
+
+/// Some value, alongside its DNSSEC validation status.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Authenticated<T> {
+    /// The looked up value.
+    pub value: T,
+    /// Whether the record the value came from was authenticated.
+    pub status: DnssecStatus,
+}
+
+
+/// A DS record, as published in the parent zone, or configured as a trust anchor.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::DelegationSigner;
+/// let ds = "20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D".parse::<DelegationSigner>().unwrap();
+/// assert_eq!(ds.key_tag, 20326);
+/// assert_eq!(ds.algorithm, 8);
+/// assert_eq!(ds.digest_type, 2);
+/// assert_eq!(ds.digest.len(), 32);
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DelegationSigner {
+    /// Key tag of the DNSKEY this refers to.
+    pub key_tag: u16,
+    /// DNSSEC algorithm number of the DNSKEY this refers to.
+    pub algorithm: u8,
+    /// Digest algorithm number: 1 for SHA-1, 2 for SHA-256, 4 for SHA-384.
+    pub digest_type: u8,
+    /// Digest of the owner name and DNSKEY RDATA.
+    pub digest: Vec<u8>,
+}
+
+impl DelegationSigner {
+    fn from_rdata(data: &[u8]) -> Option<DelegationSigner> {
+        if data.len() < 5 {
+            return None;
+        }
+        Some(DelegationSigner {
+            key_tag: ((data[0] as u16) << 8) | data[1] as u16,
+            algorithm: data[2],
+            digest_type: data[3],
+            digest: data[4..].to_vec(),
+        })
+    }
+
+    /// Check whether the digest and key algorithms are ones validation is possible with.
+    fn is_supported(&self) -> bool {
+        [1, 2, 4].contains(&self.digest_type) && [8, 10, 13, 14, 15].contains(&self.algorithm)
+    }
+
+    fn matches(&self, owner: &str, key: &Dnskey) -> bool {
+        if self.key_tag != key.tag || self.algorithm != key.algorithm {
+            return false;
+        }
+
+        let algo = match self.digest_type {
+            1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+            2 => &digest::SHA256,
+            4 => &digest::SHA384,
+            _ => return false,
+        };
+        let mut ctx = digest::Context::new(algo);
+        match wire::name_to_wire(owner) {
+            Ok(owner) => ctx.update(&owner),
+            Err(_) => return false,
+        }
+        ctx.update(&key.rdata);
+        ctx.finish().as_ref() == &self.digest[..]
+    }
+}
+
+impl FromStr for DelegationSigner {
+    type Err = Error;
+
+    /// Parse the presentation form of the DS RDATA, i.e. "key_tag algorithm digest_type hex_digest".
+    fn from_str(s: &str) -> Result<DelegationSigner, Error> {
+        let mut fields = s.split_whitespace();
+        let key_tag = fields.next().and_then(|f| f.parse().ok()).ok_or(Error::TrustAnchorParse)?;
+        let algorithm = fields.next().and_then(|f| f.parse().ok()).ok_or(Error::TrustAnchorParse)?;
+        let digest_type = fields.next().and_then(|f| f.parse().ok()).ok_or(Error::TrustAnchorParse)?;
+
+        let hex: String = fields.collect();
+        if hex.is_empty() || hex.len() & 1 != 0 {
+            return Err(Error::TrustAnchorParse);
+        }
+        let digest = (0..hex.len())
+            .step_by(2)
+            .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::TrustAnchorParse)?;
+
+        Ok(DelegationSigner {
+            key_tag,
+            algorithm,
+            digest_type,
+            digest,
+        })
+    }
+}
+
+
+/// A zone whose keys are trusted a priori, identified by DS records for them.
+///
+/// # Examples
+///
+/// Trust a locally-signed zone, say, for testing against a local name server:
+///
+/// ```
+/// # use openalias::TrustAnchor;
+/// let anchor = TrustAnchor::new("example.", &["31589 13 2 CDE0D742D6998AA554A92D890F8184C698CFAC8A26FA59875A990C03E576343C"])
+///     .unwrap();
+/// assert_eq!(anchor.zone, "example.");
+/// assert_eq!(anchor.ds[0].key_tag, 31589);
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TrustAnchor {
+    /// Absolute name of the trusted zone.
+    pub zone: String,
+    /// DS records identifying the zone's trusted keys.
+    pub ds: Vec<DelegationSigner>,
+}
+
+impl TrustAnchor {
+    /// Construct a trust anchor for the specified zone from presentation-form DS RDATA.
+    pub fn new<S: AsRef<str>>(zone: &str, ds: &[S]) -> Result<TrustAnchor, Error> {
+        let mut zone = zone.to_lowercase();
+        if !zone.ends_with('.') {
+            zone.push('.');
+        }
+
+        Ok(TrustAnchor {
+            zone,
+            ds: ds.iter().map(|d| d.as_ref().parse()).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// The [IANA root zone trust anchors](https://data.iana.org/root-anchors/root-anchors.xml) (KSK-2017 and KSK-2024).
+    pub fn root() -> TrustAnchor {
+        TrustAnchor::new(".",
+                         &["20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D",
+                           "38696 8 2 683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16"])
+            .unwrap()
+    }
+}
+
+
+/// A DNSSEC-validating OpenAlias lookup.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::Validator;
+/// let validator = Validator::new().unwrap();
+/// for record in validator.address_strings("donate@getmonero.org").unwrap() {
+///     if record.status.is_secure() {
+///         println!("{}", record.value);
+///     } else {
+///         println!("{} ({}, not trusting it)", record.value, record.status);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Validator {
+    /// Zones whose keys are trusted.
+    ///
+    /// Default: `vec![TrustAnchor::root()]`.
+    pub trust_anchors: Vec<TrustAnchor>,
+    /// Recursive name servers to ask for the records, signatures and keys; tried in order.
+    ///
+    /// Default: the system's.
+    pub name_servers: Vec<SocketAddr>,
+    /// How long to wait for each response.
+    ///
+    /// Default: 5 seconds.
+    pub timeout: Duration,
+    /// How many times to ask each server before moving on.
+    ///
+    /// Default: 2.
+    pub attempts: u32,
+}
+
+impl Validator {
+    /// Create a validator anchored at the DNS root, asking the system's name servers.
+    pub fn new() -> Result<Validator, Error> {
+        Validator::with_config(&ResolverConfig::new())
+    }
+
+    /// Create a validator anchored at the DNS root, asking the name servers from the specified config.
+    pub fn with_config(config: &ResolverConfig) -> Result<Validator, Error> {
+        Ok(Validator {
+            trust_anchors: vec![TrustAnchor::root()],
+            name_servers: config.dns_config()?.name_servers,
+            timeout: config.timeout.unwrap_or_else(|| Duration::from_secs(5)),
+            attempts: config.attempts.unwrap_or(2),
+        })
+    }
+
+    /// Look up "oa1:"-prefixed TXT records for the specified OpenAlias, and validate them.
+    pub fn address_strings(&self, address: &str) -> Result<Vec<Authenticated<String>>, Error> {
+        let lookup = self.validated_lookup(&alias_to_fqdn(address).ok_or(Error::AddressParse)?)?;
+        let status = lookup.dnssec.clone().unwrap_or(DnssecStatus::Insecure);
+
+        Ok(lookup.address_strings()?
+            .into_iter()
+            .map(|value| {
+                Authenticated {
+                    value,
+                    status: status.clone(),
+                }
+            })
+            .collect())
+    }
+
+    /// Look up and validate addresses for the specified OpenAlias.
+    pub fn addresses(&self, address: &str) -> Result<Vec<Authenticated<CryptoAddress>>, Error> {
+        self.address_strings(address)?
+            .into_iter()
+            .map(|r| {
+                Ok(Authenticated {
+                    value: r.value.parse()?,
+                    status: r.status,
+                })
+            })
+            .collect()
+    }
+
+    fn query(&self, name: &str, rtype: u16) -> Result<(SocketAddr, Transport, Message), Error> {
+        let upstream = Upstream {
+            servers: self.name_servers.clone(),
+            first: 0,
+            attempts: self.attempts,
+            timeout: self.timeout,
+        };
+        upstream.query(name, rtype, wire::FLAG_RD | wire::FLAG_CD, Some((4096, true)))
+    }
+
+    /// Look up the TXT records, with `dnssec` filled in, even if bogus.
+    ///
+    /// CNAMEs and DNAMEs are followed, and the status is the worst of theirs and the TXT records'.
+    fn validated_lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        let upstream = Upstream {
+            servers: self.name_servers.clone(),
+            first: 0,
+            attempts: self.attempts,
+            timeout: self.timeout,
+        };
+
+        let mut following = Following::new(&fqdn.to_lowercase());
+        let mut status = DnssecStatus::Secure;
+        loop {
+            let links_validated = following.links().len();
+            let (server, transport, response) =
+                upstream.query_existing(following.name(), wire::TYPE_TXT, wire::FLAG_RD | wire::FLAG_CD, Some((4096, true)))?;
+            let lookup = following.feed(&response)?;
+
+            for &(ref owner, rtype) in &following.links()[links_validated..] {
+                let (records, sigs) = rrset(&response.answers, owner, rtype);
+                status = status.worst(self.rrset_status(owner, rtype, &records, &sigs, &response.authority));
+            }
+
+            if let Some(mut lookup) = lookup {
+                let (txts, sigs) = rrset(&response.answers, following.name(), wire::TYPE_TXT);
+                if txts.is_empty() {
+                    return Err(Error::NoTxtData(lookup.fqdn));
+                }
+                status = status.worst(self.rrset_status(following.name(), wire::TYPE_TXT, &txts, &sigs, &response.authority));
+
+                lookup.name_server = Some(server);
+                lookup.transport = Some(transport);
+                lookup.dnssec = Some(status);
+                return Ok(lookup);
+            }
+        }
+    }
+
+    fn rrset_status(&self, owner: &str, rtype: u16, records: &[Record], sigs: &[Record], authority: &[Record]) -> DnssecStatus {
+        match self.zone_of(owner).and_then(|zone| zone.verify(owner, rtype, records, sigs, Some(authority))) {
+            Ok(()) => DnssecStatus::Secure,
+            Err(Failure::Insecure) => DnssecStatus::Insecure,
+            Err(Failure::Bogus(why)) => DnssecStatus::Bogus(why),
+            Err(Failure::Io(err)) => DnssecStatus::Bogus(format!("couldn't fetch chain of trust: {}", err)),
+        }
+    }
+
+    /// Walk down from the closest trust anchor to the zone the specified name is in, authenticating each delegation.
+    ///
+    /// Names without DS records must be proven to have none, and are then either within the zone above, or unsigned
+    /// delegations, below which everything is insecure.
+    fn zone_of(&self, name: &str) -> Result<Zone, Failure> {
+        let anchor = match self.trust_anchors
+            .iter()
+            .filter(|a| wire::is_subdomain(name, &a.zone))
+            .max_by_key(|a| wire::label_count(&a.zone)) {
+            Some(anchor) => anchor,
+            None => return Err(Failure::Insecure),
+        };
+        let mut zone = self.zone_keys(&anchor.zone, &anchor.ds)?;
+
+        let mut below_anchor = vec![];
+        let mut child = name;
+        while wire::label_count(child) > wire::label_count(&anchor.zone) {
+            below_anchor.push(child);
+            child = wire::parent_name(child).unwrap_or(".");
+        }
+
+        for child in below_anchor.into_iter().rev() {
+            let (_, _, response) = self.query(child, wire::TYPE_DS)?;
+            let (ds_records, ds_sigs) = rrset(&response.answers, child, wire::TYPE_DS);
+            if ds_records.is_empty() {
+                if zone.denies_ds(child, &response.authority)? {
+                    return Err(Failure::Insecure);
+                }
+                continue;
+            }
+
+            zone.verify(child, wire::TYPE_DS, &ds_records, &ds_sigs, None)?;
+            let ds: Vec<_> = ds_records.iter().filter_map(|d| DelegationSigner::from_rdata(&d.data)).filter(|d| d.is_supported()).collect();
+            if ds.is_empty() {
+                // RFC 4035 section 5.2: the child zone can't be validated, so is treated as unsigned
+                return Err(Failure::Insecure);
+            }
+            zone = self.zone_keys(child, &ds)?;
+        }
+        Ok(zone)
+    }
+
+    /// Get the DNSKEYs of the specified zone, which must be signed by one of the keys the DS records identify.
+    fn zone_keys(&self, zone: &str, ds: &[DelegationSigner]) -> Result<Zone, Failure> {
+        let (_, _, response) = self.query(zone, wire::TYPE_DNSKEY)?;
+        let (key_records, key_sigs) = rrset(&response.answers, zone, wire::TYPE_DNSKEY);
+        let keys: Vec<_> = key_records.iter().filter_map(|k| Dnskey::parse(&k.data)).collect();
+
+        let entry = Zone {
+            name: zone.to_string(),
+            keys: keys.iter().filter(|k| ds.iter().any(|d| d.matches(zone, k))).cloned().collect(),
+        };
+        if entry.keys.is_empty() {
+            return Err(Failure::Bogus(format!("no DNSKEY for {} matches its DS records", zone)));
+        }
+        entry.verify(zone, wire::TYPE_DNSKEY, &key_records, &key_sigs, None)?;
+
+        Ok(Zone {
+            name: zone.to_string(),
+            keys,
+        })
+    }
+}
+
+impl Resolver for Validator {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+    }
+
+    /// Look up the TXT records, with `dnssec` filled in; bogus records are an `Error::DnssecBogus`.
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        let lookup = self.validated_lookup(fqdn)?;
+        if let Some(DnssecStatus::Bogus(ref why)) = lookup.dnssec {
+            return Err(Error::DnssecBogus(lookup.fqdn.clone(), why.clone()));
+        }
+        Ok(lookup)
+    }
+}
+
+
+enum Failure {
+    Io(Error),
+    Insecure,
+    Bogus(String),
+}
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Failure {
+        Failure::Io(err)
+    }
+}
+
+
+/// A zone whose DNSKEYs have been authenticated.
+struct Zone {
+    name: String,
+    keys: Vec<Dnskey>,
+}
+
+impl Zone {
+    /// Check that the RRset is signed by this zone's keys.
+    ///
+    /// An RRset expanded from a wildcard is only accepted if `authority` has a proof that no closer name exists.
+    fn verify(&self, owner: &str, rtype: u16, records: &[Record], sigs: &[Record], authority: Option<&[Record]>) -> Result<(), Failure> {
+        if !wire::is_subdomain(owner, &self.name) {
+            return Err(Failure::Bogus(format!("{} not in {}", owner, self.name)));
+        }
+
+        let mut last_err = format!("no signatures by {} over type {} records for {}", self.name, rtype, owner);
+        for sig in sigs.iter().filter_map(|s| Rrsig::parse(&s.data)).filter(|s| s.type_covered == rtype) {
+            if sig.signer != self.name {
+                last_err = format!("{} signed by {}, instead of {}", owner, sig.signer, self.name);
+                continue;
+            }
+            if let Err(err) = sig.verify_with_any(owner, records, &self.keys) {
+                last_err = err;
+                continue;
+            }
+            if let Some(next_closer) = sig.next_closer(owner) {
+                if !authority.is_some_and(|authority| self.denies_name(&next_closer, authority)) {
+                    last_err = format!("{} expanded from a wildcard, without proof {} doesn't exist", owner, next_closer);
+                    continue;
+                }
+            }
+            return Ok(());
+        }
+        Err(Failure::Bogus(last_err))
+    }
+
+    /// Check whether `authority` proves the specified name doesn't have DS records: if so, whether it's a delegation,
+    /// i.e. an unsigned zone starts there, or, with NSEC3 opt-out, may.
+    fn denies_ds(&self, name: &str, authority: &[Record]) -> Result<bool, Failure> {
+        for denial in self.denials(authority) {
+            if let Some(types) = denial.types_of(name) {
+                if !wire::has_type(types, wire::TYPE_DS) {
+                    return Ok(wire::has_type(types, wire::TYPE_NS));
+                }
+            } else if denial.covers(name) {
+                return Ok(denial.opt_out);
+            }
+        }
+        Err(Failure::Bogus(format!("no proof {} has no DS records", name)))
+    }
+
+    /// Check whether `authority` proves the specified name doesn't exist.
+    fn denies_name(&self, name: &str, authority: &[Record]) -> bool {
+        self.denials(authority).iter().any(|denial| denial.denies(name))
+    }
+
+    /// Get the NSEC and NSEC3 records in `authority` signed by this zone.
+    fn denials(&self, authority: &[Record]) -> Vec<Denial> {
+        let mut owners: Vec<_> = authority.iter()
+            .filter(|r| r.rtype == wire::TYPE_NSEC || r.rtype == wire::TYPE_NSEC3)
+            .map(|r| (&r.name[..], r.rtype))
+            .collect();
+        owners.sort();
+        owners.dedup();
+
+        owners.into_iter()
+            .filter_map(|(owner, rtype)| {
+                let (records, sigs) = rrset(authority, owner, rtype);
+                if records.len() != 1 || self.verify(owner, rtype, &records, &sigs, None).is_err() {
+                    return None;
+                }
+                Denial::parse(&self.name, &records[0])
+            })
+            .collect()
+    }
+}
+
+
+/// An NSEC or NSEC3 record, stating what's at its owner, and that no names lie between it and the next one.
+#[derive(Debug, Clone)]
+struct Denial {
+    owner: String,
+    next: String,
+    /// For NSEC3, the hashing parameters, with which `owner` and `next` are the hashes, in base32hex.
+    nsec3: Option<(u16, Vec<u8>)>,
+    opt_out: bool,
+    bitmaps: Vec<u8>,
+}
+
+impl Denial {
+    fn parse(zone: &str, record: &Record) -> Option<Denial> {
+        if record.rtype == wire::TYPE_NSEC {
+            let (next, bitmaps) = wire::read_wire_name(&record.data).ok()?;
+            return Some(Denial {
+                owner: record.name.clone(),
+                next,
+                nsec3: None,
+                opt_out: false,
+                bitmaps: bitmaps.to_vec(),
+            });
+        }
+
+        // Hash algorithm, flags, iterations, salt, next hash; only SHA-1 is defined
+        let data = &record.data;
+        let salt_len = *data.get(4)? as usize;
+        let hash_len = *data.get(5 + salt_len)? as usize;
+        let iterations = ((data[2] as u16) << 8) | data[3] as u16;
+        if data[0] != 1 || iterations > MAX_NSEC3_ITERATIONS || wire::parent_name(&record.name) != Some(zone) || data.len() < 6 + salt_len + hash_len {
+            return None;
+        }
+        Some(Denial {
+            owner: record.name[..record.name.find('.')?].to_string(),
+            next: base32hex(&data[6 + salt_len..6 + salt_len + hash_len]),
+            nsec3: Some((iterations, data[5..5 + salt_len].to_vec())),
+            opt_out: data[1] & 0x01 != 0,
+            bitmaps: data[6 + salt_len + hash_len..].to_vec(),
+        })
+    }
+
+    /// Get the types at the specified name, if this record is for it.
+    fn types_of(&self, name: &str) -> Option<&[u8]> {
+        let matches = match self.nsec3 {
+            Some((iterations, ref salt)) => nsec3_hash(name, iterations, salt).as_ref() == Some(&self.owner),
+            None => name == self.owner,
+        };
+        if matches { Some(&self.bitmaps) } else { None }
+    }
+
+    /// Check whether the specified name falls strictly between this record's owner and the next one, so has no records.
+    fn covers(&self, name: &str) -> bool {
+        match self.nsec3 {
+            Some((iterations, ref salt)) => {
+                nsec3_hash(name, iterations, salt).is_some_and(|hash| between(&self.owner[..], &hash, &self.next, |a, b| a.cmp(b)))
+            }
+            None => between(&self.owner[..], name, &self.next, wire::canonical_cmp),
+        }
+    }
+
+    /// Check whether this record proves the specified name doesn't exist, i.e. covers it, and, with NSEC, it isn't an
+    /// empty non-terminal with the next name below it.
+    fn denies(&self, name: &str) -> bool {
+        self.covers(name) && (self.nsec3.is_some() || !wire::is_subdomain(&self.next, name))
+    }
+}
+
+
+#[derive(Debug, Clone)]
+struct Dnskey {
+    rdata: Vec<u8>,
+    tag: u16,
+    algorithm: u8,
+}
+
+impl Dnskey {
+    fn parse(data: &[u8]) -> Option<Dnskey> {
+        // Zone Key flag set, protocol 3
+        if data.len() < 5 || data[0] & 0x01 == 0 || data[2] != 3 {
+            return None;
+        }
+        Some(Dnskey {
+            rdata: data.to_vec(),
+            tag: key_tag(data),
+            algorithm: data[3],
+        })
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.rdata[4..]
+    }
+
+    fn verify(&self, message: &[u8], sig: &[u8]) -> Result<(), String> {
+        let key = self.public_key();
+        let result = match self.algorithm {
+            8 | 10 => {
+                let (exp_len, rest) = match key.first() {
+                    Some(&0) if key.len() >= 3 => ((((key[1] as usize) << 8) | key[2] as usize), &key[3..]),
+                    Some(&l) => (l as usize, &key[1..]),
+                    None => return Err("empty RSA key".to_string()),
+                };
+                if exp_len > rest.len() {
+                    return Err("malformed RSA key".to_string());
+                }
+                let components = RsaPublicKeyComponents {
+                    n: &rest[exp_len..],
+                    e: &rest[..exp_len],
+                };
+                components.verify(if self.algorithm == 8 {
+                                      &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY
+                                  } else {
+                                      &signature::RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY
+                                  },
+                                  message,
+                                  sig)
+            }
+            13 | 14 => {
+                let mut point = Vec::with_capacity(key.len() + 1);
+                point.push(0x04);
+                point.extend(key);
+                UnparsedPublicKey::new(if self.algorithm == 13 {
+                                           &signature::ECDSA_P256_SHA256_FIXED
+                                       } else {
+                                           &signature::ECDSA_P384_SHA384_FIXED
+                                       },
+                                       point)
+                    .verify(message, sig)
+            }
+            15 => UnparsedPublicKey::new(&signature::ED25519, key).verify(message, sig),
+            algo => return Err(format!("unsupported DNSSEC algorithm {}", algo)),
+        };
+        result.map_err(|_| "signature mismatch".to_string())
+    }
+}
+
+
+#[derive(Debug, Clone)]
+struct Rrsig {
+    type_covered: u16,
+    algorithm: u8,
+    labels: u8,
+    original_ttl: u32,
+    expiration: u32,
+    inception: u32,
+    key_tag: u16,
+    signer: String,
+    /// The RDATA up to the signature, which is the prefix of the signed data.
+    header: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Rrsig {
+    fn parse(data: &[u8]) -> Option<Rrsig> {
+        if data.len() < 19 {
+            return None;
+        }
+        let (signer, signature) = wire::read_wire_name(&data[18..]).ok()?;
+        let u32_at = |i: usize| ((data[i] as u32) << 24) | ((data[i + 1] as u32) << 16) | ((data[i + 2] as u32) << 8) | data[i + 3] as u32;
+
+        Some(Rrsig {
+            type_covered: ((data[0] as u16) << 8) | data[1] as u16,
+            algorithm: data[2],
+            labels: data[3],
+            original_ttl: u32_at(4),
+            expiration: u32_at(8),
+            inception: u32_at(12),
+            key_tag: ((data[16] as u16) << 8) | data[17] as u16,
+            header: data[..data.len() - signature.len()].to_vec(),
+            signer,
+            signature: signature.to_vec(),
+        })
+    }
+
+    fn verify_with_any(&self, owner: &str, records: &[Record], keys: &[Dnskey]) -> Result<(), String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as u32).unwrap_or(0);
+        if !serial_le(self.inception, now) {
+            return Err(format!("signature by {} not yet valid", self.signer));
+        }
+        if !serial_le(now, self.expiration) {
+            return Err(format!("signature by {} expired", self.signer));
+        }
+
+        let data = self.signed_data(owner, records)?;
+        let mut last_err = format!("no DNSKEY {} for {}", self.key_tag, self.signer);
+        for key in keys.iter().filter(|k| k.tag == self.key_tag && k.algorithm == self.algorithm) {
+            match key.verify(&data, &self.signature) {
+                Ok(()) => return Ok(()),
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Get the name just below the wildcard the RRset was expanded from, which mustn't exist, if it was.
+    fn next_closer(&self, owner: &str) -> Option<String> {
+        // The wildcard's own records aren't expanded, but don't count the "*" label either, as per RFC 4034 section 3.1.3
+        let owner_labels = wire::label_count(owner);
+        if self.labels as usize >= owner_labels || (owner.starts_with("*.") && self.labels as usize + 1 == owner_labels) {
+            return None;
+        }
+
+        let mut name = owner;
+        for _ in 0..owner_labels - self.labels as usize - 1 {
+            name = wire::parent_name(name).unwrap_or(".");
+        }
+        Some(name.to_string())
+    }
+
+    /// Build the data covered by the signature, as per RFC 4034 section 3.1.8.1.
+    fn signed_data(&self, owner: &str, records: &[Record]) -> Result<Vec<u8>, String> {
+        let owner_labels = wire::label_count(owner);
+        let owner_wire = if (self.labels as usize) < owner_labels {
+            // Wildcard expansion: the signature covers "*." + the rightmost `labels` labels
+            let mut name = owner;
+            for _ in 0..owner_labels - self.labels as usize {
+                name = wire::parent_name(name).unwrap_or(".");
+            }
+            let mut wire = vec![1, b'*'];
+            wire.extend(wire::name_to_wire(name).map_err(|err| err.to_string())?);
+            wire
+        } else if self.labels as usize == owner_labels {
+            wire::name_to_wire(owner).map_err(|err| err.to_string())?
+        } else {
+            return Err("RRSIG label count exceeds owner's".to_string());
+        };
+
+        let mut rdatas: Vec<_> = records.iter().map(|r| &r.data[..]).collect();
+        rdatas.sort();
+        rdatas.dedup();
+
+        let mut out = self.header.clone();
+        for rdata in rdatas {
+            out.extend(&owner_wire);
+            out.extend(&[(self.type_covered >> 8) as u8, self.type_covered as u8, (wire::CLASS_IN >> 8) as u8, wire::CLASS_IN as u8]);
+            out.extend(&[(self.original_ttl >> 24) as u8, (self.original_ttl >> 16) as u8, (self.original_ttl >> 8) as u8, self.original_ttl as u8]);
+            out.extend(&[(rdata.len() >> 8) as u8, rdata.len() as u8]);
+            out.extend(rdata);
+        }
+        Ok(out)
+    }
+}
+
+
+/// Extract the RRset of the specified name and type, and the RRSIGs over it, from a response section.
+fn rrset(section: &[Record], owner: &str, rtype: u16) -> (Vec<Record>, Vec<Record>) {
+    let records = section.iter().filter(|r| r.name == owner && r.rtype == rtype && r.class == wire::CLASS_IN).cloned().collect();
+    let sigs = section.iter()
+        .filter(|r| r.name == owner && r.rtype == wire::TYPE_RRSIG && r.data.len() >= 2 && ((r.data[0] as u16) << 8 | r.data[1] as u16) == rtype)
+        .cloned()
+        .collect();
+    (records, sigs)
+}
+
+/// Key tag calculation, as per RFC 4034 appendix B.
+fn key_tag(rdata: &[u8]) -> u16 {
+    let mut ac: u32 = 0;
+    for (i, &b) in rdata.iter().enumerate() {
+        ac += if i & 1 == 0 { (b as u32) << 8 } else { b as u32 };
+    }
+    ac += (ac >> 16) & 0xFFFF;
+    ac as u16
+}
+
+/// `a <= b` in RFC 1982 serial number arithmetic.
+fn serial_le(a: u32, b: u32) -> bool {
+    b.wrapping_sub(a) < 0x8000_0000
+}
+
+/// Check whether `b` is strictly between `a` and `c`, in a ring ordered by `cmp`, i.e. the last record wraps around.
+fn between<T: ?Sized, F: Fn(&T, &T) -> Ordering>(a: &T, b: &T, c: &T, cmp: F) -> bool {
+    if cmp(a, c) == Ordering::Less {
+        cmp(a, b) == Ordering::Less && cmp(b, c) == Ordering::Less
+    } else {
+        cmp(a, b) == Ordering::Less || cmp(b, c) == Ordering::Less
+    }
+}
+
+/// NSEC3 hash of a name, in base32hex, as per RFC 5155 section 5.
+fn nsec3_hash(name: &str, iterations: u16, salt: &[u8]) -> Option<String> {
+    let mut hash = wire::name_to_wire(name).ok()?;
+    for _ in 0..iterations as u32 + 1 {
+        hash.extend(salt);
+        hash = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &hash).as_ref().to_vec();
+    }
+    Some(base32hex(&hash))
+}
+
+/// Lowercase, unpadded base32 with the extended hex alphabet, as per RFC 4648 section 7.
+fn base32hex(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuv";
+
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(5));
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &b in data {
+        buffer = (buffer << 8) | b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 0x1F] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 0x1F] as char);
+    }
+    out
+}
@@ -0,0 +1,428 @@
+use self::super::{TxtRecord, Transport, Resolver, Lookup, Error};
+use self::super::resolver::lookup_following;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::io::{self, ErrorKind, Read, Write};
+use self::super::wire::{self, Message, Record};
+use self::super::tls;
+use rustls::ClientConfig;
+use std::time::Duration;
+use serde_json::Value;
+use std::sync::Arc;
+use std::str;
+
+
+/// How to phrase DNS-over-HTTPS queries.
+///
+/// # Examples
+///
+/// Against a local stand-in for Google's JSON API:
+///
+/// ```
+/// # use openalias::{DohResolver, DohFormat, Resolver};
+/// # use std::io::{BufRead, BufReader, Write};
+/// # use std::net::TcpListener;
+/// # use std::thread;
+/// let server = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("http://{}/resolve", server.local_addr().unwrap());
+///
+/// thread::spawn(move || {
+///     let (conn, _) = server.accept().unwrap();
+///     let mut request_line = String::new();
+///     BufReader::new(&conn).read_line(&mut request_line).unwrap();
+///     assert!(request_line.starts_with("GET /resolve?name=donate.example.com.&type=TXT "));
+///
+///     let body = r#"{"Status": 0, "TC": false, "AD": false, "Question": [{"name": "donate.example.com.", "type": 16}], "Answer": [
+///         {"name": "donate.example.com.", "type": 5, "TTL": 60, "data": "pay.example.net."},
+///         {"name": "pay.example.net.", "type": 16, "TTL": 300,
+///          "data": "\"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;\" \" recipient_name=example\""}]}"#;
+///     write!(&conn, "HTTP/1.1 200 OK\r\nContent-Type: application/dns-json\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+///     write!(&conn, "{:x}\r\n{}\r\n0\r\n\r\n", body.len(), body).unwrap();
+/// });
+///
+/// let resolver = DohResolver::new(&url).unwrap().format(DohFormat::Json);
+/// let lookup = resolver.lookup("donate.example.com.").unwrap();
+/// assert_eq!(lookup.cname_chain, vec!["pay.example.net.".to_string()]);
+/// assert_eq!(lookup.cname_ttl, Some(60));
+/// assert_eq!(lookup.records[0].data, &b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H; recipient_name=example"[..]);
+/// assert_eq!(lookup.records[0].ttl, Some(300));
+/// ```
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum DohFormat {
+    /// RFC 8484 DNS messages (`application/dns-message`), POSTed.
+    Wire,
+    /// The JSON API (`application/dns-json`) offered by e.g. Google and Cloudflare, over GET.
+    Json,
+}
+
+/// A resolver asking a DNS-over-HTTPS server, keeping the lookups private from the network.
+///
+/// The URL's host name is resolved with the system's resolver; specify an IP address to avoid that.
+/// `http://` URLs are accepted too, for local servers.
+///
+/// Lookups report the TTLs, the answering server, the AD bit and the CNAMEs followed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use openalias::{DohResolver, addresses_with};
+/// let resolver = DohResolver::new("https://cloudflare-dns.com/dns-query").unwrap();
+/// for address in addresses_with(&resolver, "donate@getmonero.org").unwrap() {
+///     println!("{}: {}", address.cryptocurrency, address.address);
+/// }
+/// ```
+///
+/// Against a local stand-in server:
+///
+/// ```
+/// # use openalias::{DohResolver, Resolver, Transport};
+/// # use std::io::{BufRead, BufReader, Read, Write};
+/// # use std::net::TcpListener;
+/// # use std::thread;
+/// let server = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("http://{}/dns-query", server.local_addr().unwrap());
+///
+/// let record = b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;";
+/// thread::spawn(move || {
+///     let (conn, _) = server.accept().unwrap();
+///     let mut conn = BufReader::new(conn);
+///     let mut content_length = 0;
+///     loop {
+///         let mut line = String::new();
+///         conn.read_line(&mut line).unwrap();
+///         if line == "\r\n" {
+///             break;
+///         }
+///         if line.to_lowercase().starts_with("content-length:") {
+///             content_length = line[15..].trim().parse().unwrap();
+///         }
+///     }
+///     let mut query = vec![0; content_length];
+///     conn.read_exact(&mut query).unwrap();
+///
+///     // The question, and a TXT record for it
+///     let mut response = query;
+///     response[2] |= 0x80;
+///     response[7] = 1;
+///     response.extend(&[0xC0, 12, 0, 16, 0, 1, 0, 0, 1, 44, 0, record.len() as u8 + 1, record.len() as u8]);
+///     response.extend(&record[..]);
+///
+///     let mut conn = conn.into_inner();
+///     write!(conn, "HTTP/1.1 200 OK\r\nContent-Type: application/dns-message\r\nContent-Length: {}\r\n\r\n", response.len()).unwrap();
+///     conn.write_all(&response).unwrap();
+/// });
+///
+/// let lookup = DohResolver::new(&url).unwrap().lookup("donate.example.com.").unwrap();
+/// assert_eq!(lookup.transport, Some(Transport::Https));
+/// assert_eq!(lookup.records[0].data, &record[..]);
+/// assert_eq!(lookup.records[0].ttl, Some(300));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DohResolver {
+    url: String,
+    tls: bool,
+    authority: String,
+    host: String,
+    port: u16,
+    path: String,
+    format: DohFormat,
+    timeout: Duration,
+    tls_config: Arc<ClientConfig>,
+}
+
+impl DohResolver {
+    /// Create a resolver POSTing DNS messages to the specified URL, e.g. `https://cloudflare-dns.com/dns-query`.
+    ///
+    /// The path defaults to `/dns-query`. Default timeout: 5 seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::DohResolver;
+    /// assert!(DohResolver::new("https://1.1.1.1/dns-query").is_ok());
+    /// assert!(DohResolver::new("http://[::1]:8053").is_ok());
+    /// assert!(DohResolver::new("1.1.1.1").is_err());
+    /// assert!(DohResolver::new("https://:443/dns-query").is_err());
+    /// ```
+    pub fn new(url: &str) -> Result<DohResolver, Error> {
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(Error::UrlParse(url.to_string()));
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/dns-query"),
+        };
+        let (host, port) = DohResolver::parse_authority(authority, if tls { 443 } else { 80 }).ok_or_else(|| Error::UrlParse(url.to_string()))?;
+
+        Ok(DohResolver {
+            url: url.to_string(),
+            tls,
+            authority: authority.to_string(),
+            host,
+            port,
+            path: path.to_string(),
+            format: DohFormat::Wire,
+            timeout: Duration::from_secs(5),
+            tls_config: tls::client_config(),
+        })
+    }
+
+    /// Set how to phrase queries.
+    pub fn format(mut self, format: DohFormat) -> DohResolver {
+        self.format = format;
+        self
+    }
+
+    /// Set how long to wait for each response.
+    pub fn timeout(mut self, timeout: Duration) -> DohResolver {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get the URL queries are sent to.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Split "host", "host:port", "[v6]" and "[v6]:port".
+    fn parse_authority(authority: &str, default_port: u16) -> Option<(String, u16)> {
+        let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+            let end = rest.find(']')?;
+            (&rest[..end], &rest[end + 1..])
+        } else {
+            match authority.find(':') {
+                Some(idx) => (&authority[..idx], &authority[idx..]),
+                None => (authority, ""),
+            }
+        };
+
+        let port = match port {
+            "" => default_port,
+            port => port.strip_prefix(':')?.parse().ok()?,
+        };
+        if host.is_empty() {
+            None
+        } else {
+            Some((host.to_string(), port))
+        }
+    }
+
+    fn query_wire(&self, name: &str) -> Result<(SocketAddr, Message), Error> {
+        // RFC 8484 recommends ID 0, for cacheability
+        let query = wire::build_query(0, name, wire::TYPE_TXT, wire::FLAG_RD | wire::FLAG_AD, None)?;
+        let (server, response) = self.request("POST", &self.path, "application/dns-message", &query)?;
+        if !wire::answers_query(&query, &response) {
+            return Err(wire::malformed("response doesn't match query").into());
+        }
+        Ok((server, Message::parse(&response)?))
+    }
+
+    /// Ask the JSON API, and translate the answer into a DNS message, which must be for the question asked.
+    fn query_json(&self, name: &str) -> Result<(SocketAddr, Message), Error> {
+        let target = format!("{}{}name={}&type=TXT", self.path, if self.path.contains('?') { '&' } else { '?' }, url_encode(name));
+        let (server, response) = self.request("GET", &target, "application/dns-json", &[])?;
+        let response: Value = serde_json::from_slice(&response).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+        let questions: Vec<_> = response["Question"]
+            .as_array()
+            .map(|q| &q[..])
+            .unwrap_or(&[])
+            .iter()
+            .map(|question| (absolute(question["name"].as_str().unwrap_or("")), question["type"].as_u64().unwrap_or(0) as u16, wire::CLASS_IN))
+            .collect();
+        if questions != [(name.to_lowercase(), wire::TYPE_TXT, wire::CLASS_IN)] {
+            return Err(wire::malformed("response doesn't match query").into());
+        }
+
+        let mut answers = vec![];
+        for answer in response["Answer"].as_array().map(|a| &a[..]).unwrap_or(&[]) {
+            let rtype = answer["type"].as_u64().unwrap_or(0) as u16;
+            let data = answer["data"].as_str().unwrap_or("");
+            let data = match rtype {
+                wire::TYPE_TXT => wire::txt_rdata(&TxtRecord::from_text(data.as_bytes().to_vec(), None)?.data),
+                wire::TYPE_CNAME | wire::TYPE_DNAME => wire::name_to_wire(&absolute(data))?,
+                _ => continue,
+            };
+            answers.push(Record {
+                name: absolute(answer["name"].as_str().unwrap_or("")),
+                rtype,
+                class: wire::CLASS_IN,
+                ttl: answer["TTL"].as_u64().unwrap_or(0) as u32,
+                data,
+            });
+        }
+
+        let mut flags = response["Status"].as_u64().unwrap_or(0) as u16 & 0xF;
+        if response["TC"].as_bool().unwrap_or(false) {
+            flags |= wire::FLAG_TC;
+        }
+        if response["AD"].as_bool().unwrap_or(false) {
+            flags |= wire::FLAG_AD;
+        }
+        Ok((server,
+            Message {
+                id: 0,
+                flags,
+                questions,
+                answers,
+                authority: vec![],
+                additional: vec![],
+            }))
+    }
+
+    /// Make a single HTTP/1.1 request, returning the server asked and the response body.
+    fn request(&self, method: &str, target: &str, accept: &str, body: &[u8]) -> Result<(SocketAddr, Vec<u8>), Error> {
+        let server = (&self.host[..], self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("{} has no addresses", self.host)))?;
+
+        let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nAccept: {}\r\nConnection: close\r\n",
+                                  method,
+                                  target,
+                                  self.authority,
+                                  accept)
+            .into_bytes();
+        if !body.is_empty() {
+            request.extend(format!("Content-Type: application/dns-message\r\nContent-Length: {}\r\n", body.len()).bytes());
+        }
+        request.extend(b"\r\n");
+        request.extend(body);
+
+        let response = if self.tls {
+                tls::connect(&self.tls_config, &self.host, &server, self.timeout).and_then(|conn| exchange(conn, &request))
+            } else {
+                tls::tcp_connect(&server, self.timeout).and_then(|conn| exchange(conn, &request))
+            }
+            .and_then(|response| parse_response(&response))
+            .map_err(wire::io_error)?;
+
+        match response {
+            (200, body) => Ok((server, body)),
+            (status, _) => Err(io::Error::other(format!("{} returned HTTP status {}", self.url, status)).into()),
+        }
+    }
+}
+
+impl Resolver for DohResolver {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+    }
+
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        let mut answered_by = None;
+        let mut lookup = lookup_following(fqdn, |name| {
+                let (server, response) = match self.format {
+                    DohFormat::Wire => self.query_wire(name)?,
+                    DohFormat::Json => self.query_json(name)?,
+                };
+                wire::check_rcode(response.rcode(), name, &self.url)?;
+                if response.flag(wire::FLAG_TC) {
+                    return Err(Error::Truncated(name.to_string()));
+                }
+                answered_by = Some(server);
+                Ok(response)
+            })?;
+        if lookup.records.is_empty() {
+            return Err(Error::NoTxtData(fqdn.to_string()));
+        }
+        lookup.name_server = answered_by;
+        lookup.transport = Some(Transport::Https);
+        Ok(lookup)
+    }
+}
+
+
+/// Send the request, and read everything the server sends back until it closes the connection.
+fn exchange<S: Read + Write>(mut conn: S, request: &[u8]) -> io::Result<Vec<u8>> {
+    conn.write_all(request)?;
+    conn.flush()?;
+
+    let mut response = vec![];
+    match conn.read_to_end(&mut response) {
+        Ok(_) => Ok(response),
+        // Not all servers bother with TLS close_notify
+        Err(ref err) if err.kind() == ErrorKind::UnexpectedEof && !response.is_empty() => Ok(response),
+        Err(err) => Err(err),
+    }
+}
+
+/// Get the status code and body out of an HTTP/1.1 response.
+fn parse_response(response: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+    let head_end = response.windows(4).position(|w| w == b"\r\n\r\n").ok_or_else(|| malformed("headers not terminated"))?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let mut lines = head.split("\r\n");
+    let status = lines.next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| malformed("no status code"))?;
+
+    let mut chunked = false;
+    let mut length = None;
+    for (key, value) in lines.filter_map(|l| l.split_once(':')) {
+        match &key.trim().to_lowercase()[..] {
+            "transfer-encoding" => chunked = value.trim().eq_ignore_ascii_case("chunked"),
+            "content-length" => length = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+
+    let body = &response[head_end + 4..];
+    if chunked {
+        Ok((status, dechunk(body)?))
+    } else if let Some(length) = length {
+        if body.len() < length {
+            return Err(malformed("body cut short"));
+        }
+        Ok((status, body[..length].to_vec()))
+    } else {
+        Ok((status, body.to_vec()))
+    }
+}
+
+fn dechunk(mut data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = vec![];
+    loop {
+        let line_end = data.windows(2).position(|w| w == b"\r\n").ok_or_else(|| malformed("chunk size missing"))?;
+        let size = str::from_utf8(&data[..line_end])
+            .ok()
+            .and_then(|l| usize::from_str_radix(l.split(';').next().unwrap_or("").trim(), 16).ok())
+            .ok_or_else(|| malformed("invalid chunk size"))?;
+        data = &data[line_end + 2..];
+
+        if size == 0 {
+            return Ok(out);
+        }
+        if data.len() < size + 2 {
+            return Err(malformed("chunk cut short"));
+        }
+        out.extend_from_slice(&data[..size]);
+        data = &data[size + 2..];
+    }
+}
+
+fn malformed(what: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("Malformed HTTP response: {}", what))
+}
+
+fn absolute(name: &str) -> String {
+    let mut name = name.to_lowercase();
+    if !name.ends_with('.') {
+        name.push('.');
+    }
+    name
+}
+
+/// Percent-encode everything but unreserved characters.
+fn url_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
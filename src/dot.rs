@@ -0,0 +1,163 @@
+use self::super::{Transport, Resolver, Lookup, Error};
+use self::super::resolver::lookup_following;
+use base64::engine::general_purpose::STANDARD;
+use self::super::wire::{self, Message};
+use std::net::SocketAddr;
+use self::super::tls;
+use rustls::ClientConfig;
+use std::time::Duration;
+use std::str::FromStr;
+use base64::Engine;
+use std::sync::Arc;
+use std::fmt;
+
+
+/// The SHA-256 hash of a server's SubjectPublicKeyInfo, as used for pinning in RFC 7858 and RFC 7469.
+///
+/// Get one for a server with
+///
+/// ```sh
+/// openssl s_client -connect 1.1.1.1:853 < /dev/null 2> /dev/null | openssl x509 -pubkey -noout |
+///     openssl pkey -pubin -outform der | openssl dgst -sha256 -binary | base64
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::SpkiPin;
+/// let pin: SpkiPin = "SPfg6FluPIlUc6a5h313BDCxQYNGX+THTy7ig5X3+VA=".parse().unwrap();
+/// assert_eq!(pin.0[..4], [0x48, 0xF7, 0xE0, 0xE8]);
+/// assert_eq!(pin.to_string(), "SPfg6FluPIlUc6a5h313BDCxQYNGX+THTy7ig5X3+VA=");
+///
+/// assert!("SPfg6FluPIlUc6a5h313BDCxQYNGX".parse::<SpkiPin>().is_err());
+/// assert!("not base64!".parse::<SpkiPin>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SpkiPin(pub [u8; 32]);
+
+impl FromStr for SpkiPin {
+    type Err = Error;
+
+    /// Decode a base64-encoded hash.
+    fn from_str(s: &str) -> Result<SpkiPin, Error> {
+        let hash = STANDARD.decode(s.trim()).map_err(|_| Error::SpkiPinParse)?;
+        if hash.len() != 32 {
+            return Err(Error::SpkiPinParse);
+        }
+
+        let mut pin = [0u8; 32];
+        pin.copy_from_slice(&hash);
+        Ok(SpkiPin(pin))
+    }
+}
+
+impl fmt::Display for SpkiPin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&STANDARD.encode(self.0))
+    }
+}
+
+
+/// A resolver asking a DNS-over-TLS (RFC 7858) server, keeping the lookups private from, and untampered by, the network.
+///
+/// By default, the server's certificate must be valid for its IP address (or the specified host name) and chain up to a
+/// well-known certificate authority. With SPKI pins, the server's own certificate must instead be for one of the pinned
+/// keys, and nothing else is checked; the keys of the certificates it chains up to can't be pinned.
+///
+/// Lookups report the TTLs, the answering server, the AD bit and the CNAMEs followed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use openalias::{DotResolver, SpkiPin, addresses_with};
+/// // The server's key, see SpkiPin for how to get it
+/// let pin: SpkiPin = "SPfg6FluPIlUc6a5h313BDCxQYNGX+THTy7ig5X3+VA=".parse().unwrap();
+/// let resolver = DotResolver::new("1.1.1.1:853".parse().unwrap())
+///     .host_name("one.one.one.one")
+///     .pin(pin);
+/// for address in addresses_with(&resolver, "donate@getmonero.org").unwrap() {
+///     println!("{}: {}", address.cryptocurrency, address.address);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DotResolver {
+    server: SocketAddr,
+    host_name: Option<String>,
+    pins: Vec<SpkiPin>,
+    timeout: Duration,
+    tls_config: Arc<ClientConfig>,
+}
+
+impl DotResolver {
+    /// Create a resolver asking the specified server, conventionally on port 853.
+    ///
+    /// Default timeout: 5 seconds.
+    pub fn new(server: SocketAddr) -> DotResolver {
+        DotResolver {
+            server,
+            host_name: None,
+            pins: vec![],
+            timeout: Duration::from_secs(5),
+            tls_config: tls::client_config(),
+        }
+    }
+
+    /// Expect the server's certificate to be for this name, instead of its IP address.
+    pub fn host_name<S: Into<String>>(mut self, name: S) -> DotResolver {
+        self.host_name = Some(name.into());
+        self
+    }
+
+    /// Only trust the server if its (end-entity) certificate's key has this hash.
+    ///
+    /// Can be specified multiple times, e.g. to allow for key rotation; any one pin matching is enough.
+    pub fn pin(mut self, pin: SpkiPin) -> DotResolver {
+        self.pins.push(pin);
+        self.tls_config = tls::pinned_client_config(self.pins.iter().map(|p| p.0).collect());
+        self
+    }
+
+    /// Set how long to wait for each response.
+    pub fn timeout(mut self, timeout: Duration) -> DotResolver {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get the server queries are sent to.
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Get the SPKI pins the server's key is checked against.
+    pub fn pins(&self) -> &[SpkiPin] {
+        &self.pins
+    }
+}
+
+impl Resolver for DotResolver {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+    }
+
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        let host = match self.host_name {
+            Some(ref name) => name.clone(),
+            None => self.server.ip().to_string(),
+        };
+        let mut lookup = lookup_following(fqdn, |name| {
+                let query = wire::build_query(wire::message_id(), name, wire::TYPE_TXT, wire::FLAG_RD | wire::FLAG_AD, None)?;
+                let response = tls::connect(&self.tls_config, &host, &self.server, self.timeout)
+                    .and_then(|conn| wire::exchange_stream(conn, &query))
+                    .and_then(|response| Message::parse(&response))
+                    .map_err(wire::io_error)?;
+                wire::check_rcode(response.rcode(), name, &self.server.to_string())?;
+                Ok(response)
+            })?;
+        if lookup.records.is_empty() {
+            return Err(Error::NoTxtData(fqdn.to_string()));
+        }
+        lookup.name_server = Some(self.server);
+        lookup.transport = Some(Transport::Tls);
+        Ok(lookup)
+    }
+}
@@ -1,7 +1,7 @@
 use std::error::Error as StdError;
 use std::string::FromUtf8Error;
 use std::io::Error as IoError;
-use self::super::ParseError;
+use self::super::{ParseError, FqdnError};
 use std::convert::From;
 use std::fmt;
 
@@ -17,6 +17,49 @@ pub enum Error {
     Utf8Parse(FromUtf8Error),
     /// Non-FQDN address passed to `address*()`.
     AddressParse,
+    /// Alias not a valid domain name, with what's wrong with it.
+    FqdnParse(FqdnError),
+    /// Trust anchor DS record not in "key_tag algorithm digest_type hex_digest" format.
+    TrustAnchorParse,
+    /// The specified FQDN doesn't exist (NXDOMAIN), i.e. the alias is wrong.
+    NxDomain(String),
+    /// The specified FQDN exists, but has no TXT records.
+    NoTxtData(String),
+    /// No name server responded in time.
+    Timeout,
+    /// A timeout of zero was specified, so no response could ever be waited for.
+    ZeroTimeout,
+    /// The specified name server failed to look the FQDN up (SERVFAIL).
+    ServerFailure(String),
+    /// The specified name server refused to look the FQDN up (REFUSED).
+    Refused(String),
+    /// The response for the specified FQDN was truncated, and couldn't be gotten in full.
+    Truncated(String),
+    /// The records for the specified FQDN failed DNSSEC validation, with the reason why.
+    DnssecBogus(String, String),
+    /// TXT record text not valid presentation format, with the reason why.
+    TxtParse(String),
+    /// The specified DNS-over-HTTPS URL not valid.
+    UrlParse(String),
+    /// SPKI pin not a base64-encoded SHA-256 hash.
+    SpkiPinParse,
+    /// The CNAME/DNAME chain starting at the specified FQDN loops, or is too long.
+    CnameChain(String),
+    /// Zone file not valid, with the line number and the reason why.
+    ZoneParse(usize, String),
+    /// Overrides file not valid, with the line number and the reason why.
+    OverrideParse(usize, String),
+    /// Monero address not valid, with the reason why.
+    MoneroAddressParse(String),
+    /// Transaction amount not valid, with the reason why.
+    AmountParse(String),
+}
+
+impl Error {
+    /// Check whether retrying the lookup later could help, i.e. this is a DNS server problem, rather than the alias'.
+    pub fn is_transient(&self) -> bool {
+        matches!(*self, Error::Io(_) | Error::Timeout | Error::ServerFailure(_) | Error::Refused(_) | Error::Truncated(_))
+    }
 }
 
 impl From<ParseError> for Error {
@@ -25,6 +68,12 @@ impl From<ParseError> for Error {
     }
 }
 
+impl From<FqdnError> for Error {
+    fn from(fe: FqdnError) -> Error {
+        Error::FqdnParse(fe)
+    }
+}
+
 impl From<IoError> for Error {
     fn from(ioe: IoError) -> Error {
         Error::Io(ioe)
@@ -38,21 +87,13 @@ impl From<FromUtf8Error> for Error {
 }
 
 impl StdError for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Oa1Parse(ref pe) => pe.description(),
-            Error::Io(ref ioe) => ioe.description(),
-            Error::Utf8Parse(ref u8e) => u8e.description(),
-            Error::AddressParse => "Specified address not valid OpenAlias",
-        }
-    }
-
-    fn cause(&self) -> Option<&StdError> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match *self {
             Error::Oa1Parse(ref pe) => Some(pe),
+            Error::FqdnParse(ref fe) => Some(fe),
             Error::Io(ref ioe) => Some(ioe),
             Error::Utf8Parse(ref u8e) => Some(u8e),
-            Error::AddressParse => None,
+            _ => None,
         }
     }
 }
@@ -63,7 +104,25 @@ impl fmt::Display for Error {
             Error::Oa1Parse(ref pe) => write!(f, "{}", pe),
             Error::Io(ref ioe) => write!(f, "{}", ioe),
             Error::Utf8Parse(ref u8e) => write!(f, "{}", u8e),
-            Error::AddressParse => write!(f, "{}", self.description()),
+            Error::AddressParse => f.write_str("Specified address not valid OpenAlias"),
+            Error::FqdnParse(ref fe) => write!(f, "Specified address not valid OpenAlias: {}", fe),
+            Error::TrustAnchorParse => f.write_str("Specified trust anchor not a valid DS record"),
+            Error::NxDomain(ref fqdn) => write!(f, "{} does not exist", fqdn),
+            Error::NoTxtData(ref fqdn) => write!(f, "{} has no TXT records", fqdn),
+            Error::Timeout => f.write_str("DNS server timed out"),
+            Error::ZeroTimeout => f.write_str("Timeout must be longer than zero"),
+            Error::ServerFailure(ref server) => write!(f, "DNS server {} failed", server),
+            Error::Refused(ref server) => write!(f, "DNS server {} refused to answer", server),
+            Error::Truncated(ref fqdn) => write!(f, "Response for {} truncated", fqdn),
+            Error::DnssecBogus(ref fqdn, ref why) => write!(f, "DNSSEC validation for {} failed: {}", fqdn, why),
+            Error::TxtParse(ref why) => write!(f, "Malformed TXT record: {}", why),
+            Error::UrlParse(ref url) => write!(f, "{} is not a valid DNS-over-HTTPS URL", url),
+            Error::SpkiPinParse => f.write_str("Specified SPKI pin not a base64-encoded SHA-256 hash"),
+            Error::CnameChain(ref fqdn) => write!(f, "CNAME chain from {} loops or is too long", fqdn),
+            Error::ZoneParse(line, ref why) => write!(f, "Malformed zone file, line {}: {}", line, why),
+            Error::OverrideParse(line, ref why) => write!(f, "Malformed overrides file, line {}: {}", line, why),
+            Error::MoneroAddressParse(ref why) => write!(f, "Malformed Monero address: {}", why),
+            Error::AmountParse(ref why) => write!(f, "Malformed transaction amount: {}", why),
         }
     }
 }
@@ -0,0 +1,110 @@
+use unicode_security::{MixedScript, skeleton};
+use self::super::{alias_to_fqdn, alias_to_unicode};
+use std::cmp;
+use std::fmt;
+
+
+/// Something making an alias look like it could be another one, e.g. in a phishing attempt.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum HomographWarning {
+    /// The specified label mixes scripts, e.g. Latin and Cyrillic.
+    MixedScript(String),
+    /// The specified non-ASCII character looks just like the specified ASCII text.
+    Confusable(char, String),
+    /// The alias looks like, but isn't, the specified trusted alias.
+    LooksLike(String),
+}
+
+impl fmt::Display for HomographWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HomographWarning::MixedScript(ref label) => write!(f, "label {} mixes scripts", label),
+            HomographWarning::Confusable(c, ref like) => write!(f, "{} (U+{:04X}) looks like {}", c, c as u32, like),
+            HomographWarning::LooksLike(ref trusted) => write!(f, "looks like {}, but isn't", trusted),
+        }
+    }
+}
+
+
+/// Check whether the specified alias could be mistaken for another one.
+///
+/// The alias is checked in its Unicode form for labels mixing scripts, as defined by
+/// [UTS #39](https://www.unicode.org/reports/tr39/#Mixed_Script_Detection), and for characters confusable with ASCII ones;
+/// and compared against the trusted aliases, warning if it has the same UTS #39 skeleton as, or is one edit away from,
+/// one of them, without being it.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{HomographWarning, homograph_warnings};
+/// let trusted = ["donate@getmonero.org"];
+/// assert_eq!(homograph_warnings("donate@getmonero.org", &trusted), vec![]);
+/// assert_eq!(homograph_warnings("datki@żółw.pl", &trusted), vec![]);
+///
+/// // Cyrillic "о"
+/// assert_eq!(homograph_warnings("donate@getm\u{43E}nero.org", &trusted),
+///            vec![HomographWarning::MixedScript("getm\u{43E}nero".to_string()),
+///                 HomographWarning::Confusable('\u{43E}', "o".to_string()),
+///                 HomographWarning::LooksLike("donate@getmonero.org".to_string())]);
+/// // Same, but as looked up
+/// assert_eq!(homograph_warnings("donate@xn--getmnero-qbh.org", &trusted).len(), 3);
+///
+/// assert_eq!(homograph_warnings("donate@getrnonero.org", &trusted),
+///            vec![HomographWarning::LooksLike("donate@getmonero.org".to_string())]);
+/// assert_eq!(homograph_warnings("donate@getmomero.org", &trusted),
+///            vec![HomographWarning::LooksLike("donate@getmonero.org".to_string())]);
+/// ```
+pub fn homograph_warnings<S: AsRef<str>>(alias: &str, trusted: &[S]) -> Vec<HomographWarning> {
+    let name = unicode_name(alias);
+    let mut warnings = vec![];
+
+    for label in name.split('.') {
+        if !label.is_single_script() {
+            warnings.push(HomographWarning::MixedScript(label.to_string()));
+        }
+    }
+
+    let mut seen = vec![];
+    for c in name.chars().filter(|c| !c.is_ascii()) {
+        let like: String = skeleton(c.encode_utf8(&mut [0; 4])).collect();
+        if like.is_ascii() && !seen.contains(&c) {
+            seen.push(c);
+            warnings.push(HomographWarning::Confusable(c, like));
+        }
+    }
+
+    let name_skeleton: Vec<char> = skeleton(&name).collect();
+    for trusted in trusted {
+        let trusted_name = unicode_name(trusted.as_ref());
+        if trusted_name == name {
+            continue;
+        }
+        let trusted_skeleton: Vec<char> = skeleton(&trusted_name).collect();
+        if edit_distance(&name_skeleton, &trusted_skeleton) <= 1 {
+            warnings.push(HomographWarning::LooksLike(trusted.as_ref().to_string()));
+        }
+    }
+
+    warnings
+}
+
+
+/// Get the alias' FQDN, lowercase, with U-labels and no final dot, for comparison.
+fn unicode_name(alias: &str) -> String {
+    let fqdn = alias_to_fqdn(alias).unwrap_or_else(|| alias.to_string());
+    alias_to_unicode(fqdn.trim_end_matches('.')).to_lowercase()
+}
+
+/// Levenshtein distance.
+fn edit_distance(lhs: &[char], rhs: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..rhs.len() + 1).collect();
+    for (i, l) in lhs.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, r) in rhs.iter().enumerate() {
+            let substitution = previous[j] + if l == r { 0 } else { 1 };
+            current.push(cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1));
+        }
+        previous = current;
+    }
+    previous[rhs.len()]
+}
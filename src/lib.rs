@@ -24,7 +24,14 @@
 //! }
 //! ```
 //!
-//! Consult the [`alias_to_fqdn()`](fn.alias_to_fqdn.html) documentation for more information and examples.
+//! Consult the [`alias_to_fqdn()`](fn.alias_to_fqdn.html) documentation for more information and examples,
+//! and use [`alias_to_fqdn_strict()`](fn.alias_to_fqdn_strict.html) to also reject aliases that aren't valid domain names.
+//! To store and compare aliases, parse them into an [`OpenAlias`](struct.OpenAlias.html).
+//! To check whether an alias could be a look-alike of another, use [`homograph_warnings()`](fn.homograph_warnings.html).
+//! To check whether an address is valid for its cryptocurrency, use [`AddressValidators`](struct.AddressValidators.html),
+//! and whether it was signed by its owner for the alias, use [`CryptoAddress::verify_signature()`](struct.CryptoAddress.html#method.verify_signature).
+//! To get a record's `tx_amount` as an exact number of the cryptocurrency's smallest unit, use
+//! [`CryptoAddress::amount()`](struct.CryptoAddress.html#method.amount).
 //!
 //! ```
 //! # use std::collections::BTreeMap;
@@ -104,6 +111,26 @@
 //! Consult the [`address_strings()`](fn.address_strings.html) and [`addresses()`](fn.addresses.html)
 //! documentation for more information and examples.
 //!
+//! To get at the well-formed records of an alias that also has malformed ones, use
+//! [`addresses_lenient()`](fn.addresses_lenient.html).
+//!
+//! To get the records' TTLs, the answering name server, the CNAMEs followed et al., use [`lookup()`](fn.lookup.html).
+//!
+//! To look up via something other than the system's DNS servers, pass a [`Resolver`](trait.Resolver.html)
+//! to [`address_strings_with()`](fn.address_strings_with.html) or [`addresses_with()`](fn.addresses_with.html).
+//! To avoid asking again for recently looked up aliases, wrap it in a [`CachingResolver`](struct.CachingResolver.html).
+//! To use local records for some aliases, wrap it in an [`OverridingResolver`](struct.OverridingResolver.html).
+//! To keep lookups private from the network, use a [`DohResolver`](struct.DohResolver.html)
+//! or a [`DotResolver`](struct.DotResolver.html).
+//!
+//! To look up many aliases at once, use [`batch_address_strings()`](fn.batch_address_strings.html) and
+//! [`batch_addresses()`](fn.batch_addresses.html), et al.
+//!
+//! With the `async` feature, non-blocking counterparts are available as [`address_strings_async()`](fn.address_strings_async.html)
+//! and [`addresses_async()`](fn.addresses_async.html), et al.
+//!
+//! Neither of those check the records' DNSSEC signatures, for lookups that do, see [`Validator`](struct.Validator.html).
+//!
 //! # openalias.rs as аn executable
 //!
 //! This is just a very short synopsis of
@@ -118,6 +145,18 @@
 //! | --verbose                | Print more data about what's happenning to stderr.    |
 //! | --raw                    | Print just the record text.                           |
 //! | --currency=[CURRENCY]... | Limit results to specified currencies.                |
+//! | --server=[NAME_SERVER]...| Ask these name servers instead of the system's.       |
+//! | --timeout=[SECONDS]      | Wait at most this long for each DNS response.         |
+//! | --attempts=[ATTEMPTS]    | Ask the name servers at most this many times.         |
+//! | --jobs=[JOBS]            | Look up at most this many aliases at once.            |
+//! | --doh=[URL]              | Ask this DNS-over-HTTPS server instead.               |
+//! | --doh-json               | Use the JSON DNS-over-HTTPS API.                      |
+//! | --dot=[SERVER]           | Ask this DNS-over-TLS server instead.                 |
+//! | --dot-host=[NAME]        | Expect the DNS-over-TLS certificate for this name.    |
+//! | --spki-pin=[PIN]...      | Only trust DNS-over-TLS servers with these keys.      |
+//! | --zone-file=[FILE]       | Answer from this zone file instead of the DNS.        |
+//! | --overrides=[FILE]       | Use these local records for the aliases in this file. |
+//! | --trusted=[ALIAS]...     | Warn about aliases that look like these.              |
 //!
 //! ## EXAMPLES
 //!
@@ -175,17 +214,69 @@ extern crate resolve;
 #[macro_use]
 extern crate clap;
 extern crate crc;
+extern crate ring;
+extern crate rustls;
+extern crate serde_json;
+extern crate base64;
+extern crate idna;
+extern crate unicode_security;
+extern crate tiny_keccak;
+extern crate bs58;
+extern crate bech32;
+extern crate base58_monero;
+extern crate k256;
+extern crate ripemd;
+extern crate curve25519_dalek;
+extern crate webpki_roots;
+#[cfg(feature = "async")]
+extern crate tokio;
 
+mod doh;
+mod dot;
+mod tls;
+mod wire;
+mod zone;
+mod batch;
+mod cache;
 mod error;
+mod dnssec;
+mod lookup;
 mod grammar;
 mod address;
 mod options;
+mod resolver;
+mod overrides;
+mod homograph;
+mod validation;
+mod monero;
+mod signature;
+mod amount;
 mod resolving;
 mod crypto_addr;
+#[cfg(feature = "async")]
+mod async_lookup;
 
 pub use self::error::Error;
 pub use self::options::Options;
 pub use self::grammar::ParseError;
-pub use self::address::alias_to_fqdn;
+pub use self::address::{OpenAlias, FqdnError, alias_to_fqdn, alias_to_fqdn_strict, alias_to_unicode};
 pub use self::crypto_addr::CryptoAddress;
-pub use self::resolving::{address_strings, addresses};
+pub use self::resolver::{Resolver, ResolverConfig, DnsResolver};
+pub use self::cache::{CachingResolver, CacheEntry};
+pub use self::doh::{DohResolver, DohFormat};
+pub use self::dot::{DotResolver, SpkiPin};
+pub use self::zone::ZoneFile;
+pub use self::overrides::{Overrides, OverridingResolver};
+pub use self::homograph::{HomographWarning, homograph_warnings};
+pub use self::validation::{AddressValidator, AddressValidators, AddressValidity};
+pub use self::monero::{MoneroAddress, MoneroAddressType, MoneroNetwork};
+pub use self::signature::SignatureValidity;
+pub use self::amount::Amount;
+pub use self::lookup::{Lookup, TxtRecord, Transport};
+pub use self::resolving::{ParsedRecord, address_strings, address_strings_with, addresses, addresses_with, addresses_lenient,
+                          addresses_lenient_with, lookup, lookup_with};
+pub use self::batch::{batch_address_strings, batch_address_strings_with, batch_addresses, batch_addresses_with, batch_lookups, batch_lookups_with};
+#[cfg(feature = "async")]
+pub use self::async_lookup::{LookupFuture, address_strings_async, address_strings_with_async, address_strings_with_config_async, addresses_async,
+                             addresses_with_async, addresses_with_config_async};
+pub use self::dnssec::{DnssecStatus, Authenticated, DelegationSigner, TrustAnchor, Validator};
@@ -0,0 +1,201 @@
+use self::super::{CryptoAddress, DnssecStatus, Error};
+use self::super::wire;
+use std::iter::FromIterator;
+use std::net::SocketAddr;
+use std::fmt;
+
+
+/// Everything a TXT lookup for an alias found out.
+///
+/// Resolvers fill in as much of the metadata as they know, see [`Resolver::lookup()`](trait.Resolver.html#method.lookup).
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::lookup;
+/// let lookup = lookup("donate@getmonero.org").unwrap();
+/// println!("{} answered by {:?}, via {:?}", lookup.fqdn, lookup.name_server, lookup.cname_chain);
+/// for record in lookup.oa1_records() {
+///     println!("{} (TTL {:?})", String::from_utf8_lossy(&record.data), record.ttl);
+/// }
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Lookup {
+    /// The FQDN queried.
+    pub fqdn: String,
+    /// All TXT records found.
+    pub records: Vec<TxtRecord>,
+    /// The name server which answered, if known.
+    pub name_server: Option<SocketAddr>,
+    /// How the answer was gotten, if over the network.
+    pub transport: Option<Transport>,
+    /// Whether the name server claimed to have validated the answer with DNSSEC (the AD bit).
+    ///
+    /// Only meaningful if the path to the server is trusted.
+    pub authenticated_data: bool,
+    /// Result of local DNSSEC validation, if performed (see [`Validator`](struct.Validator.html)).
+    pub dnssec: Option<DnssecStatus>,
+    /// Names the CNAMEs and DNAMEs followed to get to the records redirected to, in order, not including `fqdn`.
+    ///
+    /// The records are really those of the last one, if any.
+    pub cname_chain: Vec<String>,
+    /// The lowest TTL among the CNAMEs and DNAMEs in `cname_chain`, if known.
+    ///
+    /// The chain may lead elsewhere after that long, however long the records themselves live.
+    pub cname_ttl: Option<u32>,
+    /// Whether the records are local stand-ins instead of a DNS answer (see [`Overrides`](struct.Overrides.html)).
+    pub overridden: bool,
+}
+
+/// How a DNS response was received.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Transport {
+    /// Plain UDP, the usual.
+    Udp,
+    /// Plain TCP, after the UDP response came back truncated.
+    Tcp,
+    /// DNS-over-HTTPS (see [`DohResolver`](struct.DohResolver.html)).
+    Https,
+    /// DNS-over-TLS (see [`DotResolver`](struct.DotResolver.html)).
+    Tls,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Transport::Udp => "UDP",
+            Transport::Tcp => "TCP",
+            Transport::Https => "HTTPS",
+            Transport::Tls => "TLS",
+        })
+    }
+}
+
+
+/// A single TXT record.
+///
+/// A TXT record is made of one or more character-strings of at most 255 bytes each, so longer OpenAlias records
+/// (e.g. ones with an `address_signature`) are split up when published; `data` is them all joined together, which is
+/// what the record means.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TxtRecord {
+    /// The record's text.
+    pub data: Vec<u8>,
+    /// The record's TTL in seconds, if known.
+    pub ttl: Option<u32>,
+}
+
+impl TxtRecord {
+    /// Join the character-strings of a TXT record.
+    ///
+    /// The segments are joined as bytes, so it doesn't matter where they were split.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::TxtRecord;
+    /// // Split in the middle of a UTF-8 sequence
+    /// let record = TxtRecord::from_segments(&[&b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H; recipient_name=Caf\xC3"[..],
+    ///                                         &b"\xA9;"[..]],
+    ///                                       Some(300));
+    /// assert_eq!(record.data, "oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H; recipient_name=Café;".as_bytes());
+    /// assert_eq!(record.ttl, Some(300));
+    ///
+    /// // The segments are taken as-is, so a \ ending one doesn't make an escape with the next one's digits
+    /// assert_eq!(TxtRecord::from_segments(&[&b"oa1:btc tx_description=a\\"[..], &b"059"[..]], None).data,
+    ///            b"oa1:btc tx_description=a\\059");
+    /// ```
+    pub fn from_segments<I, S>(segments: I, ttl: Option<u32>) -> TxtRecord
+        where I: IntoIterator<Item = S>,
+              S: AsRef<[u8]>
+    {
+        TxtRecord {
+            data: segments.into_iter().fold(vec![], |mut data, s| {
+                data.extend_from_slice(s.as_ref());
+                data
+            }),
+            ttl,
+        }
+    }
+
+    /// Parse a TXT record in presentation format, as in zone files, `dig` output and many DNS libraries' text.
+    ///
+    /// That is: whitespace-separated character-strings, each optionally in double quotes,
+    /// with `\X` standing for `X` and `\DDD` for the byte with decimal value `DDD`.
+    /// Each segment is unescaped on its own, and the results are joined as bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::TxtRecord;
+    /// let record = TxtRecord::from_presentation(r#""oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H\;" "recipient_name=\"Caf\195" "\169\"\059""#,
+    ///                                           None).unwrap();
+    /// assert_eq!(record.data, r#"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;recipient_name="Café";"#.as_bytes());
+    ///
+    /// // Segments can be unquoted, too
+    /// assert_eq!(TxtRecord::from_presentation("oa1:xmr\\032recipient_address=4 6BeWrHpw", None).unwrap().data,
+    ///            b"oa1:xmr recipient_address=46BeWrHpw");
+    ///
+    /// // An escaped quote doesn't end the segment
+    /// assert_eq!(TxtRecord::from_presentation(r#""oa1:btc tx_description=\"" "x\"""#, None).unwrap().data,
+    ///            br#"oa1:btc tx_description="x""#);
+    ///
+    /// // Even right before where the segment would end, so the quote opening the next one ends it instead,
+    /// // leaving b" unquoted
+    /// assert_eq!(TxtRecord::from_presentation(r#""oa1:btc tx_description=a\" "b""#, None).unwrap().data,
+    ///            br#"oa1:btc tx_description=a" b""#);
+    ///
+    /// // A \DDD escape doesn't continue into the next segment
+    /// assert!(TxtRecord::from_presentation(r#""oa1:btc recipient_name=Caf\19" "5\169""#, None).is_err());
+    /// assert_eq!(TxtRecord::from_presentation(r#""oa1:btc recipient_name=Caf\195" "\169""#, None).unwrap().data,
+    ///            b"oa1:btc recipient_name=Caf\xC3\xA9");
+    ///
+    /// assert!(TxtRecord::from_presentation(r#""oa1:btc \19"#, None).is_err());
+    /// assert!(TxtRecord::from_presentation(r#""oa1:btc \256""#, None).is_err());
+    /// assert!(TxtRecord::from_presentation(r#""oa1:btc"#, None).is_err());
+    /// ```
+    pub fn from_presentation<T: AsRef<[u8]>>(text: T, ttl: Option<u32>) -> Result<TxtRecord, Error> {
+        Ok(TxtRecord::from_segments(wire::character_strings(text.as_ref()).map_err(Error::TxtParse)?, ttl))
+    }
+
+    /// Parse the record if it's in presentation format (i.e. starts with a `"`), otherwise take it verbatim.
+    pub(crate) fn from_text(data: Vec<u8>, ttl: Option<u32>) -> Result<TxtRecord, Error> {
+        if data.starts_with(b"\"") {
+            TxtRecord::from_presentation(data, ttl)
+        } else {
+            Ok(TxtRecord { data, ttl })
+        }
+    }
+}
+
+impl Lookup {
+    /// Create a lookup result with the specified records and no metadata.
+    pub fn new(fqdn: String, records: Vec<TxtRecord>) -> Lookup {
+        Lookup {
+            fqdn,
+            records,
+            name_server: None,
+            transport: None,
+            authenticated_data: false,
+            dnssec: None,
+            cname_chain: vec![],
+            cname_ttl: None,
+            overridden: false,
+        }
+    }
+
+    /// Get the "oa1:"-prefixed records.
+    pub fn oa1_records(&self) -> Vec<&TxtRecord> {
+        self.records.iter().filter(|r| r.data.starts_with(b"oa1:")).collect()
+    }
+
+    /// Get the text of the "oa1:"-prefixed records.
+    pub fn address_strings(&self) -> Result<Vec<String>, Error> {
+        Ok(Result::from_iter(self.oa1_records().into_iter().map(|r| String::from_utf8(r.data.clone())))?)
+    }
+
+    /// Parse the "oa1:"-prefixed records.
+    pub fn addresses(&self) -> Result<Vec<CryptoAddress>, Error> {
+        Ok(Result::from_iter(self.address_strings()?.into_iter().map(|s| s.parse()))?)
+    }
+}
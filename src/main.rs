@@ -1,6 +1,6 @@
 extern crate openalias;
 
-use openalias::{Options, Error};
+use openalias::{DohResolver, DotResolver, DohFormat, ZoneFile, Overrides, Resolver, Options, OpenAlias, Lookup, Error, AddressValidators, AddressValidity, SignatureValidity, homograph_warnings};
 use std::process::exit;
 
 
@@ -28,17 +28,54 @@ fn actual_main() -> i32 {
 fn result_main() -> Result<(), Error> {
     let opts = Options::parse();
 
-    for addr in opts.aliases {
-        if opts.verbose {
+    if opts.verbose {
+        for addr in &opts.aliases {
             eprintln!("Looking up {}...", addr);
         }
+    }
+    let overrides = match opts.overrides {
+        Some(ref path) => Overrides::open(path)?,
+        None => Overrides::new(),
+    };
+    let overridden: Vec<_> = opts.aliases.iter().map(|addr| overrides.lookup(&addr.fqdn())).collect();
+    let to_look_up: Vec<_> = opts.aliases.iter().zip(&overridden).filter(|&(_, o)| o.is_none()).map(|(addr, _)| addr).collect();
+
+    let resolver = custom_resolver(&opts)?;
+    let validators = AddressValidators::new();
+    let mut results = match resolver {
+            Some(ref resolver) => openalias::batch_lookups_with(&**resolver, &to_look_up, opts.jobs),
+            None => openalias::batch_lookups(&to_look_up, &opts.resolver_config, opts.jobs),
+        }
+        .into_iter();
+
+    for (alias, overridden) in opts.aliases.iter().zip(overridden) {
+        for warning in homograph_warnings(alias, &opts.trusted_aliases) {
+            println!("Warning: {}: {}.", display_name(alias), warning);
+        }
+        let addr = display_name(alias);
+        let lookup = found(overridden.map(Ok).unwrap_or_else(|| results.next().unwrap()))?;
+        let flag = if lookup.as_ref().is_some_and(|l| l.overridden) {
+            " (overridden)"
+        } else {
+            ""
+        };
+        if let Some(ref lookup) = lookup {
+            let mut from = &lookup.fqdn;
+            for target in &lookup.cname_chain {
+                println!("{} is an alias for {}.", from.trim_end_matches('.'), target.trim_end_matches('.'));
+                from = target;
+            }
+        }
 
         if opts.raw {
-            let mut raddrs = openalias::address_strings(&addr)?;
+            let mut raddrs = match lookup {
+                Some(ref lookup) => lookup.address_strings()?,
+                None => vec![],
+            };
             if raddrs.is_empty() {
                 println!("No records found for {}.", addr);
             } else {
-                if let Some(ref currency_filter) = opts.currency_filter.as_ref() {
+                if let Some(currency_filter) = opts.currency_filter.as_ref() {
                     raddrs.retain(|raddr| currency_filter.iter().any(|curr| raddr[4..].starts_with(curr)));
                     if raddrs.is_empty() {
                         print!("No ");
@@ -56,19 +93,22 @@ fn result_main() -> Result<(), Error> {
                 }
 
                 if !raddrs.is_empty() {
-                    println!("Addresses for {}:", addr);
+                    println!("Addresses for {}{}:", addr, flag);
                     for raddr in raddrs {
                         println!("  {}", raddr);
                     }
                 }
             }
         } else {
-            let mut caddrs = openalias::addresses(&addr)?;
+            let mut caddrs = match lookup {
+                Some(ref lookup) => lookup.addresses()?,
+                None => vec![],
+            };
             if caddrs.is_empty() {
                 println!("No addresses found for {}.", addr);
             } else {
-                if let Some(ref currency_filter) = opts.currency_filter.as_ref() {
-                    caddrs.retain(|caddr| currency_filter.iter().any(|curr| caddr.cryptocurrency == *curr));
+                if let Some(currency_filter) = opts.currency_filter.as_ref() {
+                    caddrs.retain(|caddr| currency_filter.contains(&caddr.cryptocurrency));
                     if caddrs.is_empty() {
                         print!("No ");
                         for (id, ref curr) in currency_filter.iter().enumerate() {
@@ -85,8 +125,10 @@ fn result_main() -> Result<(), Error> {
                 }
 
                 if !caddrs.is_empty() {
-                    println!("Addresses of {}:", addr);
+                    println!("Addresses of {}{}:", addr, flag);
                     for caddr in caddrs {
+                        let validity = validators.validate(&caddr);
+                        let signature_validity = caddr.verify_signature(alias);
                         println!("  {}:", caddr.cryptocurrency);
                         if let Some(recipient_name) = caddr.recipient_name.as_ref() {
                             maybe_escape!(recipient_name, "    {q}{}{q}", recipient_name);
@@ -101,6 +143,14 @@ fn result_main() -> Result<(), Error> {
                         if let Some(tx_payment_id) = caddr.tx_payment_id.as_ref() {
                             maybe_escape!(tx_payment_id, "    {q}{}{q}", tx_payment_id);
                         }
+                        if let Ok(Some(integrated)) = caddr.integrated_address() {
+                            let integrated = integrated.to_string();
+                            if integrated == caddr.address {
+                                println!("    Integrated address");
+                            } else {
+                                println!("    Integrated address: {}", integrated);
+                            }
+                        }
                         if let Some(address_signature) = caddr.address_signature.as_ref() {
                             maybe_escape!(address_signature, "    {q}{}{q}", address_signature);
                         }
@@ -110,6 +160,16 @@ fn result_main() -> Result<(), Error> {
                         if let Some(&(_, checksum_ok)) = caddr.checksum.as_ref() {
                             println!("    Checksum {}", if checksum_ok { "OK" } else { "INCORRECT" });
                         }
+                        match validity {
+                            AddressValidity::Valid => println!("    Address OK"),
+                            AddressValidity::Invalid(why) => println!("    Address INVALID: {}", why),
+                            AddressValidity::Unknown => {}
+                        }
+                        match signature_validity {
+                            SignatureValidity::Valid => println!("    Signature OK"),
+                            SignatureValidity::Invalid(why) => println!("    Signature INVALID: {}", why),
+                            SignatureValidity::Unsupported => {}
+                        }
                     }
                 }
             }
@@ -120,3 +180,53 @@ fn result_main() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Get the zone file, DNS-over-HTTPS or DNS-over-TLS resolver, if one was asked for.
+fn custom_resolver(opts: &Options) -> Result<Option<Box<dyn Resolver + Sync>>, Error> {
+    if let Some(ref path) = opts.zone_file {
+        Ok(Some(Box::new(ZoneFile::open(path)?)))
+    } else if let Some(ref url) = opts.doh {
+        let mut resolver = DohResolver::new(url)?.format(if opts.doh_json {
+            DohFormat::Json
+        } else {
+            DohFormat::Wire
+        });
+        if let Some(timeout) = opts.resolver_config.timeout {
+            resolver = resolver.timeout(timeout);
+        }
+        Ok(Some(Box::new(resolver)))
+    } else if let Some(server) = opts.dot {
+        let mut resolver = DotResolver::new(server);
+        if let Some(ref host) = opts.dot_host {
+            resolver = resolver.host_name(host.clone());
+        }
+        for &pin in &opts.spki_pins {
+            resolver = resolver.pin(pin);
+        }
+        if let Some(timeout) = opts.resolver_config.timeout {
+            resolver = resolver.timeout(timeout);
+        }
+        Ok(Some(Box::new(resolver)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Show internationalised aliases in both Unicode and as looked up.
+fn display_name(alias: &OpenAlias) -> String {
+    let unicode = alias.to_unicode();
+    if unicode != **alias {
+        format!("{} ({})", unicode, alias)
+    } else {
+        alias.to_string()
+    }
+}
+
+/// A domain without TXT records just has no addresses.
+fn found(result: Result<Lookup, Error>) -> Result<Option<Lookup>, Error> {
+    match result {
+        Ok(lookup) => Ok(Some(lookup)),
+        Err(Error::NoTxtData(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,207 @@
+use self::super::validation::keccak256;
+use self::super::Error;
+use std::str::FromStr;
+use base58_monero;
+use std::fmt;
+
+
+/// The Monero network an address is for.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoneroNetwork {
+    /// The real one.
+    Mainnet,
+    /// For testing with the mainnet's rules.
+    Stagenet,
+    /// For testing new features.
+    Testnet,
+}
+
+/// The kind of a Monero address.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoneroAddressType {
+    /// A wallet's main address, starting with "4" on the mainnet.
+    Standard,
+    /// A wallet's derived address, starting with "8" on the mainnet.
+    Subaddress,
+    /// A standard address with a short payment ID, so the recipient can tell payments apart.
+    Integrated,
+}
+
+impl fmt::Display for MoneroNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            MoneroNetwork::Mainnet => "mainnet",
+            MoneroNetwork::Stagenet => "stagenet",
+            MoneroNetwork::Testnet => "testnet",
+        })
+    }
+}
+
+impl fmt::Display for MoneroAddressType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            MoneroAddressType::Standard => "standard",
+            MoneroAddressType::Subaddress => "subaddress",
+            MoneroAddressType::Integrated => "integrated",
+        })
+    }
+}
+
+
+/// A decoded Monero address.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{MoneroAddress, MoneroAddressType, MoneroNetwork};
+/// let address: MoneroAddress =
+///     "46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em".parse().unwrap();
+/// assert_eq!(address.network, MoneroNetwork::Mainnet);
+/// assert_eq!(address.address_type, MoneroAddressType::Standard);
+/// assert_eq!(address.payment_id, None);
+///
+/// let integrated = address.integrated([0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0]).unwrap();
+/// assert_eq!(integrated.address_type, MoneroAddressType::Integrated);
+/// assert_eq!(integrated.public_spend_key, address.public_spend_key);
+///
+/// let integrated_str = integrated.to_string();
+/// assert_eq!(integrated_str.len(), 106);
+/// let integrated: MoneroAddress = integrated_str.parse().unwrap();
+/// assert_eq!(integrated.payment_id, Some([0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0]));
+/// assert_eq!(integrated.standard(), address);
+///
+/// assert!("46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16en".parse::<MoneroAddress>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MoneroAddress {
+    /// The network the address is for.
+    pub network: MoneroNetwork,
+    /// The kind of address.
+    pub address_type: MoneroAddressType,
+    /// The public spend key.
+    pub public_spend_key: [u8; 32],
+    /// The public view key.
+    pub public_view_key: [u8; 32],
+    /// The short payment ID, for integrated addresses.
+    pub payment_id: Option<[u8; 8]>,
+}
+
+impl MoneroAddress {
+    /// Combine a standard address with a short payment ID into an integrated address.
+    ///
+    /// Subaddresses can't be integrated, and integrated addresses get their payment ID replaced.
+    pub fn integrated(&self, payment_id: [u8; 8]) -> Result<MoneroAddress, Error> {
+        if self.address_type == MoneroAddressType::Subaddress {
+            return Err(Error::MoneroAddressParse("subaddresses can't have payment IDs".to_string()));
+        }
+
+        Ok(MoneroAddress {
+            address_type: MoneroAddressType::Integrated,
+            payment_id: Some(payment_id),
+            ..*self
+        })
+    }
+
+    /// Get the standard address an integrated address is for, or the address itself otherwise.
+    pub fn standard(&self) -> MoneroAddress {
+        if self.address_type != MoneroAddressType::Integrated {
+            return *self;
+        }
+
+        MoneroAddress {
+            address_type: MoneroAddressType::Standard,
+            payment_id: None,
+            ..*self
+        }
+    }
+
+    fn network_byte(&self) -> u8 {
+        NETWORK_BYTES.iter().find(|&&(_, n, t)| n == self.network && t == self.address_type).expect("all networks and types have bytes").0
+    }
+}
+
+impl FromStr for MoneroAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<MoneroAddress, Error> {
+        let data = base58_monero::decode(s).map_err(|e| Error::MoneroAddressParse(format!("not Monero base58: {}", e)))?;
+        if data.len() < 5 {
+            return Err(Error::MoneroAddressParse(format!("{} bytes long", data.len())));
+        }
+
+        let (payload, checksum) = data.split_at(data.len() - 4);
+        if keccak256(payload)[..4] != *checksum {
+            return Err(Error::MoneroAddressParse("checksum mismatch".to_string()));
+        }
+
+        let (network, address_type) = match NETWORK_BYTES.iter().find(|&&(b, _, _)| b == payload[0]) {
+            Some(&(_, network, address_type)) => (network, address_type),
+            None => return Err(Error::MoneroAddressParse(format!("unknown network byte {}", payload[0]))),
+        };
+        let expected_len = if address_type == MoneroAddressType::Integrated {
+            1 + 32 + 32 + 8
+        } else {
+            1 + 32 + 32
+        };
+        if payload.len() != expected_len {
+            return Err(Error::MoneroAddressParse(format!("{} bytes long, instead of {}", payload.len(), expected_len)));
+        }
+
+        let mut address = MoneroAddress {
+            network,
+            address_type,
+            public_spend_key: [0; 32],
+            public_view_key: [0; 32],
+            payment_id: None,
+        };
+        address.public_spend_key.copy_from_slice(&payload[1..33]);
+        address.public_view_key.copy_from_slice(&payload[33..65]);
+        if address_type == MoneroAddressType::Integrated {
+            let mut payment_id = [0; 8];
+            payment_id.copy_from_slice(&payload[65..]);
+            address.payment_id = Some(payment_id);
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Display for MoneroAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut data = vec![self.network_byte()];
+        data.extend_from_slice(&self.public_spend_key);
+        data.extend_from_slice(&self.public_view_key);
+        if let Some(ref payment_id) = self.payment_id {
+            data.extend_from_slice(payment_id);
+        }
+        let checksum = keccak256(&data);
+        data.extend_from_slice(&checksum[..4]);
+
+        f.write_str(&base58_monero::encode(&data).expect("encoding to memory can't fail"))
+    }
+}
+
+
+/// Parse a short (16 hex digit) payment ID.
+pub(crate) fn parse_payment_id(s: &str) -> Option<[u8; 8]> {
+    if s.len() != 16 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut payment_id = [0; 8];
+    for (i, b) in payment_id.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    Some(payment_id)
+}
+
+
+/// Address prefixes, as in Monero's `cryptonote_config.h`.
+static NETWORK_BYTES: &[(u8, MoneroNetwork, MoneroAddressType)] = &[(18, MoneroNetwork::Mainnet, MoneroAddressType::Standard),
+                                                                     (19, MoneroNetwork::Mainnet, MoneroAddressType::Integrated),
+                                                                     (42, MoneroNetwork::Mainnet, MoneroAddressType::Subaddress),
+                                                                     (24, MoneroNetwork::Stagenet, MoneroAddressType::Standard),
+                                                                     (25, MoneroNetwork::Stagenet, MoneroAddressType::Integrated),
+                                                                     (36, MoneroNetwork::Stagenet, MoneroAddressType::Subaddress),
+                                                                     (53, MoneroNetwork::Testnet, MoneroAddressType::Standard),
+                                                                     (54, MoneroNetwork::Testnet, MoneroAddressType::Integrated),
+                                                                     (63, MoneroNetwork::Testnet, MoneroAddressType::Subaddress)];
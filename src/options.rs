@@ -11,15 +11,18 @@
 //! ```
 
 
-use self::super::alias_to_fqdn;
+use self::super::{ResolverConfig, DohResolver, OpenAlias, SpkiPin, alias_to_fqdn_strict};
+use std::net::{SocketAddr, IpAddr};
 use clap::{AppSettings, Arg};
+use std::time::Duration;
+use std::path::PathBuf;
 
 
 /// Representation of the application's all configurable values.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Options {
     /// Aliases to look up.
-    pub aliases: Vec<String>,
+    pub aliases: Vec<OpenAlias>,
     /// Print more information.
     ///
     /// Default: `false`.
@@ -32,6 +35,46 @@ pub struct Options {
     ///
     /// Default: `None`.
     pub currency_filter: Option<Vec<String>>,
+    /// How to talk to DNS servers.
+    ///
+    /// Default: defer to the system configuration.
+    pub resolver_config: ResolverConfig,
+    /// How many aliases to look up at once.
+    ///
+    /// Default: `8`.
+    pub jobs: usize,
+    /// DNS-over-HTTPS URL to ask instead of name servers.
+    ///
+    /// Default: `None`.
+    pub doh: Option<String>,
+    /// Use the JSON DNS-over-HTTPS API instead of DNS messages.
+    ///
+    /// Default: `false`.
+    pub doh_json: bool,
+    /// DNS-over-TLS server to ask instead of name servers.
+    ///
+    /// Default: `None`.
+    pub dot: Option<SocketAddr>,
+    /// Name the DNS-over-TLS server's certificate must be for, instead of its IP address.
+    ///
+    /// Default: `None`.
+    pub dot_host: Option<String>,
+    /// Only trust the DNS-over-TLS server if its key has one of these hashes.
+    ///
+    /// Default: empty, i.e. trust certificates from well-known authorities.
+    pub spki_pins: Vec<SpkiPin>,
+    /// Zone file to answer from instead of the DNS.
+    ///
+    /// Default: `None`.
+    pub zone_file: Option<PathBuf>,
+    /// Overrides file whose records to use instead of looking the aliases therein up.
+    ///
+    /// Default: `None`.
+    pub overrides: Option<PathBuf>,
+    /// Warn about aliases looking like, but not being, one of these.
+    ///
+    /// Default: empty.
+    pub trusted_aliases: Vec<OpenAlias>,
 }
 
 impl Options {
@@ -43,17 +86,102 @@ impl Options {
             .arg(Arg::from_usage("-v --verbose 'Print out more information'"))
             .arg(Arg::from_usage("-r --raw 'Print just the record text'"))
             .arg(Arg::from_usage("-c --currency=[CURRENCY]... 'Limit results to just CURRENCY'"))
+            .arg(Arg::from_usage("-s --server=[NAME_SERVER]... 'Ask NAME_SERVER instead of the system name servers'")
+                .number_of_values(1)
+                .validator(Options::name_server_validator))
+            .arg(Arg::from_usage("-t --timeout=[SECONDS] 'Wait at most SECONDS for each DNS response'").validator(Options::timeout_validator))
+            .arg(Arg::from_usage("-a --attempts=[ATTEMPTS] 'Ask the name servers at most ATTEMPTS times'").validator(Options::u32_validator))
+            .arg(Arg::from_usage("-j --jobs=[JOBS] 'Look up at most JOBS aliases at once'").default_value("8").validator(Options::jobs_validator))
+            .arg(Arg::from_usage("--doh=[URL] 'Ask the DNS-over-HTTPS server at URL instead of name servers'")
+                .conflicts_with("server")
+                .validator(Options::doh_validator))
+            .arg(Arg::from_usage("--doh-json 'Use the JSON DNS-over-HTTPS API'").requires("doh"))
+            .arg(Arg::from_usage("--dot=[SERVER] 'Ask the DNS-over-TLS server SERVER instead of name servers'")
+                .conflicts_with_all(&["server", "doh"])
+                .validator(Options::dot_server_validator))
+            .arg(Arg::from_usage("--dot-host=[NAME] 'Expect the DNS-over-TLS server's certificate to be for NAME'").requires("dot"))
+            .arg(Arg::from_usage("--spki-pin=[PIN]... 'Only trust the DNS-over-TLS server if its key has hash PIN'")
+                .number_of_values(1)
+                .requires("dot")
+                .validator(Options::spki_pin_validator))
+            .arg(Arg::from_usage("--zone-file=[FILE] 'Answer from the zone file FILE instead of the DNS'").conflicts_with_all(&["server", "doh", "dot"]))
+            .arg(Arg::from_usage("--overrides=[FILE] 'Use the records in FILE for the aliases therein instead of looking them up'"))
+            .arg(Arg::from_usage("--trusted=[ALIAS]... 'Warn about aliases looking like, but not being, ALIAS'")
+                .number_of_values(1)
+                .validator(Options::open_alias_validator))
             .get_matches();
 
+        let mut resolver_config = ResolverConfig::new();
+        if let Some(servers) = matches.values_of("server") {
+            resolver_config.name_servers = servers.map(|s| Options::parse_server(s, 53).unwrap()).collect();
+        }
+        if let Some(timeout) = matches.value_of("timeout") {
+            resolver_config.timeout = Some(Duration::from_secs(timeout.parse().unwrap()));
+        }
+        if let Some(attempts) = matches.value_of("attempts") {
+            resolver_config.attempts = Some(attempts.parse().unwrap());
+        }
+
         Options {
-            aliases: matches.values_of("OPEN_ALIAS").unwrap().map(String::from).collect(),
+            aliases: matches.values_of("OPEN_ALIAS").unwrap().map(|a| a.parse().unwrap()).collect(),
             verbose: matches.is_present("verbose"),
             raw: matches.is_present("raw"),
             currency_filter: matches.values_of("currency").map(|cs| cs.map(String::from).collect()),
+            resolver_config,
+            jobs: matches.value_of("jobs").unwrap().parse().unwrap(),
+            doh: matches.value_of("doh").map(String::from),
+            doh_json: matches.is_present("doh-json"),
+            dot: matches.value_of("dot").map(|s| Options::parse_server(s, 853).unwrap()),
+            dot_host: matches.value_of("dot-host").map(String::from),
+            spki_pins: matches.values_of("spki-pin").map(|ps| ps.map(|p| p.parse().unwrap()).collect()).unwrap_or_default(),
+            zone_file: matches.value_of("zone-file").map(PathBuf::from),
+            overrides: matches.value_of("overrides").map(PathBuf::from),
+            trusted_aliases: matches.values_of("trusted").map(|ts| ts.map(|t| t.parse().unwrap()).collect()).unwrap_or_default(),
         }
     }
 
+    /// Accept "IP" and "IP:port" addresses, defaulting to the specified port.
+    fn parse_server(s: &str, default_port: u16) -> Option<SocketAddr> {
+        s.parse().ok().or_else(|| s.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, default_port)))
+    }
+
+    fn name_server_validator(s: String) -> Result<(), String> {
+        Options::parse_server(&s, 53).map(|_| ()).ok_or_else(|| format!("{} is not a valid name server address", s))
+    }
+
+    fn dot_server_validator(s: String) -> Result<(), String> {
+        Options::parse_server(&s, 853).map(|_| ()).ok_or_else(|| format!("{} is not a valid DNS-over-TLS server address", s))
+    }
+
+    fn spki_pin_validator(s: String) -> Result<(), String> {
+        s.parse::<SpkiPin>().map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn jobs_validator(s: String) -> Result<(), String> {
+        match s.parse::<usize>() {
+            Ok(0) => Err("at least one alias must be looked up at once".to_string()),
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{} is not a valid number: {}", s, e)),
+        }
+    }
+
+    fn timeout_validator(s: String) -> Result<(), String> {
+        match s.parse::<u32>() {
+            Ok(0) => Err("responses must be waited for at least one second".to_string()),
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{} is not a valid number: {}", s, e)),
+        }
+    }
+
+    fn doh_validator(s: String) -> Result<(), String> {
+        DohResolver::new(&s).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn u32_validator(s: String) -> Result<(), String> {
+        s.parse::<u32>().map(|_| ()).map_err(|e| format!("{} is not a valid number: {}", s, e))
+    }
+
     fn open_alias_validator(s: String) -> Result<(), String> {
-        alias_to_fqdn(&s).map(|_| ()).ok_or_else(|| format!("{} is not a valid OpenAlias address", s))
+        alias_to_fqdn_strict(&s).map(|_| ()).map_err(|e| format!("{} is not a valid OpenAlias address: {}", s, e))
     }
 }
@@ -0,0 +1,158 @@
+use self::super::{Resolver, TxtRecord, Lookup, Error, alias_to_fqdn};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::path::Path;
+use std::fs;
+
+
+/// Local records standing in for aliases' DNS ones, e.g. to point production aliases at test-net addresses when staging.
+///
+/// The file format is like `/etc/hosts`: each line is an alias (or FQDN), whitespace, and an `oa1:` record, with
+/// everything after a `#` at the start of a line being a comment. An alias can have many lines, one per record.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::Overrides;
+/// let overrides: Overrides = "
+///     ## Staging
+///     donate@ourcompany.com  oa1:btc recipient_address=mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt; recipient_name=Test-net;
+///     donate.ourcompany.com  oa1:xmr recipient_address=9wviCeWe2D8XS82k2ovp5EUYLzBt9pYNW2LXUFsZiv8S3Mt21FZ5qQaAroko1enzw3eGr9qC7X1D7Geoo2RrAotYPx1iovY;
+/// ".parse().unwrap();
+///
+/// let lookup = overrides.lookup("DONATE.ourcompany.com.").unwrap();
+/// assert!(lookup.overridden);
+/// assert_eq!(lookup.addresses().unwrap().len(), 2);
+///
+/// assert!(overrides.lookup("donate.example.com.").is_none());
+/// assert!("donate@ourcompany.com btc:mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt".parse::<Overrides>().is_err());
+/// ```
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+pub struct Overrides {
+    records: BTreeMap<String, Vec<Vec<u8>>>,
+}
+
+impl Overrides {
+    /// Create an empty set of overrides.
+    pub fn new() -> Overrides {
+        Overrides::default()
+    }
+
+    /// Read and parse the specified overrides file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Overrides, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Add a record for the specified alias (or FQDN), in addition to any it already has.
+    ///
+    /// Fails if the alias isn't valid.
+    pub fn insert<R: Into<Vec<u8>>>(&mut self, alias: &str, record: R) -> Result<(), Error> {
+        let fqdn = alias_to_fqdn(alias).ok_or(Error::AddressParse)?.to_lowercase();
+        self.records.entry(fqdn).or_default().push(record.into());
+        Ok(())
+    }
+
+    /// Get the FQDNs with overridden records.
+    pub fn fqdns(&self) -> Vec<&str> {
+        self.records.keys().map(|k| &k[..]).collect()
+    }
+
+    /// Get the overridden records for the specified FQDN, if any, as a lookup marked [`overridden`](struct.Lookup.html#structfield.overridden).
+    pub fn lookup(&self, fqdn: &str) -> Option<Lookup> {
+        self.records.get(&fqdn.to_lowercase()).map(|records| {
+            let mut lookup = Lookup::new(fqdn.to_string(),
+                                         records.iter()
+                                             .map(|r| {
+                                                 TxtRecord {
+                                                     data: r.clone(),
+                                                     ttl: None,
+                                                 }
+                                             })
+                                             .collect());
+            lookup.overridden = true;
+            lookup
+        })
+    }
+}
+
+impl FromStr for Overrides {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Overrides, Error> {
+        let mut overrides = Overrides::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (alias, record) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+            let record = record.trim();
+            if !record.starts_with("oa1:") {
+                return Err(Error::OverrideParse(i + 1, format!("no oa1: record for {}", alias)));
+            }
+            overrides.insert(alias, record).map_err(|_| Error::OverrideParse(i + 1, format!("{} is not a valid OpenAlias address", alias)))?;
+        }
+        Ok(overrides)
+    }
+}
+
+
+/// A resolver answering from [`Overrides`](struct.Overrides.html) where there are any, and asking another resolver otherwise.
+///
+/// Overridden lookups are marked [`overridden`](struct.Lookup.html#structfield.overridden), and have no other metadata.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{OverridingResolver, Overrides, Error, lookup_with};
+/// let upstream = |_: &str| -> Result<Vec<Vec<u8>>, Error> {
+///     Ok(vec![b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;".to_vec()])
+/// };
+/// let mut overrides = Overrides::new();
+/// overrides.insert("donate@ourcompany.com", "oa1:btc recipient_address=mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt;").unwrap();
+/// let resolver = OverridingResolver::new(overrides, upstream);
+///
+/// let lookup = lookup_with(&resolver, "donate@ourcompany.com").unwrap();
+/// assert!(lookup.overridden);
+/// assert_eq!(lookup.addresses().unwrap()[0].address, "mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt");
+///
+/// let lookup = lookup_with(&resolver, "donate@example.com").unwrap();
+/// assert!(!lookup.overridden);
+/// assert_eq!(lookup.addresses().unwrap()[0].address, "1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H");
+/// ```
+#[derive(Debug, Clone)]
+pub struct OverridingResolver<R: Resolver> {
+    overrides: Overrides,
+    resolver: R,
+}
+
+impl<R: Resolver> OverridingResolver<R> {
+    /// Answer from the specified overrides before asking the specified resolver.
+    pub fn new(overrides: Overrides, resolver: R) -> OverridingResolver<R> {
+        OverridingResolver { overrides, resolver }
+    }
+
+    /// Get the overrides.
+    pub fn overrides(&self) -> &Overrides {
+        &self.overrides
+    }
+
+    /// Get the wrapped resolver.
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+}
+
+impl<R: Resolver> Resolver for OverridingResolver<R> {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+    }
+
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        match self.overrides.lookup(fqdn) {
+            Some(lookup) => Ok(lookup),
+            None => self.resolver.lookup(fqdn),
+        }
+    }
+}
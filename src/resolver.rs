@@ -0,0 +1,509 @@
+use self::super::wire::{self, Message, Record, Upstream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use self::super::{TxtRecord, Transport, Lookup, Error};
+use resolve::{DnsConfig, default_config};
+use std::iter::FromIterator;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+
+/// Most CNAMEs and DNAMEs followed from a name before giving up.
+const MAX_CHAIN_LENGTH: usize = 16;
+
+
+/// A source of TXT records.
+///
+/// Implement this to use a different DNS backend, a caching layer, or canned answers with
+/// [`address_strings_with()`](fn.address_strings_with.html) and [`addresses_with()`](fn.addresses_with.html).
+///
+/// Closures of the right signature are resolvers, too.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{Error, address_strings_with};
+/// let fake = |fqdn: &str| -> Result<Vec<Vec<u8>>, Error> {
+///     assert_eq!(fqdn, "donate.example.com.");
+///     Ok(vec![b"v=spf1 -all".to_vec(), b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;".to_vec()])
+/// };
+///
+/// assert_eq!(address_strings_with(&fake, "donate@example.com").unwrap(),
+///            vec!["oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;".to_string()]);
+/// ```
+///
+/// Records split into multiple character-strings can be returned in presentation format:
+///
+/// ```
+/// # use openalias::{Error, address_strings_with};
+/// let fake = |_: &str| -> Result<Vec<Vec<u8>>, Error> {
+///     Ok(vec![br#""oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H\059" " tx_description=\"50\037 off\"\;""#.to_vec()])
+/// };
+///
+/// assert_eq!(address_strings_with(&fake, "donate@example.com").unwrap(),
+///            vec![r#"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H; tx_description="50% off";"#.to_string()]);
+/// ```
+pub trait Resolver {
+    /// Get the data of all TXT records for the specified FQDN.
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error>;
+
+    /// Get all TXT records for the specified FQDN, alongside whatever is known about the lookup.
+    ///
+    /// The default implementation wraps [`txt_records()`](#tymethod.txt_records) with no metadata.
+    /// Records starting with a `"` are taken to be in presentation format, as many DNS libraries return them,
+    /// and are [unescaped and joined](struct.TxtRecord.html#method.from_presentation).
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        Ok(Lookup::new(fqdn.to_string(),
+                       Result::from_iter(self.txt_records(fqdn)?.into_iter().map(|data| TxtRecord::from_text(data, None)))?))
+    }
+}
+
+impl<F: Fn(&str) -> Result<Vec<Vec<u8>>, Error>> Resolver for F {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        self(fqdn)
+    }
+}
+
+
+/// Settings for talking to DNS servers.
+///
+/// Unset values are taken from the system's configuration,
+/// and no servers other than the specified or system ones are ever asked, unless explicitly configured as fallback.
+///
+/// # Examples
+///
+/// Ask only a local resolver, without consulting the system configuration at all:
+///
+/// ```
+/// # use openalias::{DnsResolver, ResolverConfig};
+/// # use std::time::Duration;
+/// let config = ResolverConfig::new()
+///     .name_server("127.0.0.1:53".parse().unwrap())
+///     .timeout(Duration::from_secs(2))
+///     .attempts(3);
+/// assert_eq!(config.name_servers, vec!["127.0.0.1:53".parse().unwrap()]);
+/// assert_eq!(config.timeout, Some(Duration::from_secs(2)));
+///
+/// let mut config = config;
+/// config.timeout = Some(Duration::from_secs(0));
+/// assert!(DnsResolver::with_config(&config).is_err());
+/// ```
+///
+/// Use the system's name servers, but Google's public ones if those can't be determined (what `address_strings()` used to do):
+///
+/// ```
+/// # use openalias::ResolverConfig;
+/// let config = ResolverConfig::new().fallback_name_servers(ResolverConfig::google_public_dns());
+/// assert!(config.name_servers.is_empty());
+/// assert_eq!(config.fallback_name_servers.len(), 2);
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct ResolverConfig {
+    /// Name servers to ask, in order.
+    ///
+    /// Default: empty, i.e. the system's.
+    pub name_servers: Vec<SocketAddr>,
+    /// Name servers to ask if `name_servers` is empty and the system's can't be determined.
+    ///
+    /// Default: empty, i.e. fail instead.
+    pub fallback_name_servers: Vec<SocketAddr>,
+    /// How long to wait for each response.
+    ///
+    /// Default: `None`, i.e. the system's, or 5 seconds.
+    pub timeout: Option<Duration>,
+    /// How many times to ask the servers.
+    ///
+    /// Default: `None`, i.e. the system's, or 5.
+    pub attempts: Option<u32>,
+    /// Whether to spread queries across the servers, instead of asking them in order.
+    ///
+    /// Default: `None`, i.e. the system's, or `true`.
+    pub rotate: Option<bool>,
+    /// Whether to prefer IPv6, asking IPv6 name servers before IPv4 ones, otherwise in order.
+    ///
+    /// Default: `None`, i.e. the system's, or `false`.
+    pub use_inet6: Option<bool>,
+    /// The UDP payload size to advertise with EDNS0, `Some(0)` to not use EDNS0 (limiting UDP responses to 512 bytes).
+    ///
+    /// Responses that don't fit are retried over TCP regardless.
+    ///
+    /// Default: `None`, i.e. 1232.
+    pub edns_payload_size: Option<u16>,
+}
+
+impl ResolverConfig {
+    /// Get the default config, deferring to the system for everything.
+    pub fn new() -> ResolverConfig {
+        ResolverConfig::default()
+    }
+
+    /// Google's public DNS servers, 8.8.8.8 and 8.8.4.4.
+    pub fn google_public_dns() -> Vec<SocketAddr> {
+        vec!["8.8.8.8:53".parse().unwrap(), "8.8.4.4:53".parse().unwrap()]
+    }
+
+    /// Add a name server to ask.
+    pub fn name_server(mut self, server: SocketAddr) -> ResolverConfig {
+        self.name_servers.push(server);
+        self
+    }
+
+    /// Set the name servers to ask if the system's can't be determined.
+    pub fn fallback_name_servers(mut self, servers: Vec<SocketAddr>) -> ResolverConfig {
+        self.fallback_name_servers = servers;
+        self
+    }
+
+    /// Set the response timeout.
+    ///
+    /// A zero timeout is rejected when a resolver is built from the config.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::{Error, ResolverConfig, Validator};
+    /// # use std::time::Duration;
+    /// let config = ResolverConfig::new().timeout(Duration::from_secs(0));
+    /// assert!(matches!(Validator::with_config(&config), Err(Error::ZeroTimeout)));
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> ResolverConfig {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the amount of attempts.
+    pub fn attempts(mut self, attempts: u32) -> ResolverConfig {
+        self.attempts = Some(attempts);
+        self
+    }
+
+    /// Set whether to rotate between servers.
+    pub fn rotate(mut self, rotate: bool) -> ResolverConfig {
+        self.rotate = Some(rotate);
+        self
+    }
+
+    /// Set whether to prefer IPv6 name servers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::{ResolverConfig, Validator};
+    /// let config = ResolverConfig::new()
+    ///     .name_server("127.0.0.1:53".parse().unwrap())
+    ///     .name_server("[::1]:53".parse().unwrap())
+    ///     .use_inet6(true);
+    /// assert_eq!(Validator::with_config(&config).unwrap().name_servers,
+    ///            vec!["[::1]:53".parse().unwrap(), "127.0.0.1:53".parse().unwrap()]);
+    /// ```
+    pub fn use_inet6(mut self, use_inet6: bool) -> ResolverConfig {
+        self.use_inet6 = Some(use_inet6);
+        self
+    }
+
+    /// Set the UDP payload size to advertise with EDNS0, 0 to not use EDNS0.
+    pub fn edns_payload_size(mut self, size: u16) -> ResolverConfig {
+        self.edns_payload_size = Some(size);
+        self
+    }
+
+    /// Merge the settings with the system configuration (if needed) into something `resolve` understands.
+    pub(crate) fn dns_config(&self) -> Result<DnsConfig, Error> {
+        if self.timeout == Some(Duration::from_secs(0)) {
+            return Err(Error::ZeroTimeout);
+        }
+
+        let mut config = if self.name_servers.is_empty() {
+            match default_config() {
+                Ok(config) => config,
+                Err(_) if !self.fallback_name_servers.is_empty() => ResolverConfig::base_config(self.fallback_name_servers.clone()),
+                Err(err) => return Err(err.into()),
+            }
+        } else {
+            ResolverConfig::base_config(self.name_servers.clone())
+        };
+
+        if let Some(timeout) = self.timeout {
+            config.timeout = timeout;
+        }
+        if let Some(attempts) = self.attempts {
+            config.attempts = attempts;
+        }
+        if let Some(rotate) = self.rotate {
+            config.rotate = rotate;
+        }
+        if let Some(use_inet6) = self.use_inet6 {
+            config.use_inet6 = use_inet6;
+        }
+        if config.use_inet6 {
+            config.name_servers.sort_by_key(|server| server.is_ipv4());
+        }
+        Ok(config)
+    }
+
+    fn base_config(name_servers: Vec<SocketAddr>) -> DnsConfig {
+        DnsConfig {
+            name_servers,
+            search: vec![],
+            n_dots: 0,
+            timeout: Duration::from_secs(5),
+            attempts: 5,
+            rotate: true,
+            use_inet6: false,
+        }
+    }
+}
+
+
+/// The default resolver, asking DNS servers directly.
+///
+/// Lookups report the TTLs, the answering server, the transport, the AD bit and the CNAMEs followed.
+///
+/// Queries advertise a large EDNS0 UDP payload size, and answers that still come back truncated are asked for again over TCP.
+///
+/// # Examples
+///
+/// A name server only able to give the whole answer over TCP:
+///
+/// ```
+/// # use openalias::{DnsResolver, ResolverConfig, Resolver, Transport};
+/// # use std::net::{TcpListener, UdpSocket};
+/// # use std::io::{Read, Write};
+/// # use std::thread;
+/// let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let server = tcp.local_addr().unwrap();
+/// let udp = UdpSocket::bind(server).unwrap();
+///
+/// let record = b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;";
+/// let answer = move |query: &[u8], truncated: bool| {
+///     // Header and question for donate.example.com., without the OPT record
+///     let mut response = query[..12 + 20 + 4].to_vec();
+///     response[2] |= 0x80;
+///     response[11] = 0;
+///     if truncated {
+///         response[2] |= 0x02;
+///     } else {
+///         response[7] = 1;
+///         response.extend(&[0xC0, 12, 0, 16, 0, 1, 0, 0, 1, 44, 0, record.len() as u8 + 1, record.len() as u8]);
+///         response.extend(&record[..]);
+///     }
+///     response
+/// };
+///
+/// thread::spawn(move || {
+///     let mut buf = [0; 512];
+///     let (len, from) = udp.recv_from(&mut buf).unwrap();
+///     udp.send_to(&answer(&buf[..len], true), from).unwrap();
+///
+///     let (mut conn, _) = tcp.accept().unwrap();
+///     let mut len = [0; 2];
+///     conn.read_exact(&mut len).unwrap();
+///     let mut query = vec![0; u16::from_be_bytes(len) as usize];
+///     conn.read_exact(&mut query).unwrap();
+///     let response = answer(&query, false);
+///     conn.write_all(&(response.len() as u16).to_be_bytes()).unwrap();
+///     conn.write_all(&response).unwrap();
+/// });
+///
+/// let resolver = DnsResolver::with_config(&ResolverConfig::new().name_server(server)).unwrap();
+/// let lookup = resolver.lookup("donate.example.com.").unwrap();
+/// assert_eq!(lookup.transport, Some(Transport::Tcp));
+/// assert_eq!(lookup.records[0].data, &record[..]);
+/// ```
+#[derive(Debug)]
+pub struct DnsResolver {
+    name_servers: Vec<SocketAddr>,
+    timeout: Duration,
+    attempts: u32,
+    rotate: bool,
+    edns_payload_size: u16,
+    next_server: AtomicUsize,
+}
+
+impl DnsResolver {
+    /// Create a resolver asking the system's name servers.
+    ///
+    /// Fails if those can't be determined, use [`with_config()`](#method.with_config) to specify some.
+    pub fn new() -> Result<DnsResolver, Error> {
+        DnsResolver::with_config(&ResolverConfig::new())
+    }
+
+    /// Create a resolver with the specified settings.
+    pub fn with_config(config: &ResolverConfig) -> Result<DnsResolver, Error> {
+        let dns_config = config.dns_config()?;
+        Ok(DnsResolver {
+            name_servers: dns_config.name_servers,
+            timeout: dns_config.timeout,
+            attempts: dns_config.attempts,
+            rotate: dns_config.rotate,
+            edns_payload_size: config.edns_payload_size.unwrap_or(1232),
+            next_server: AtomicUsize::new(0),
+        })
+    }
+
+    /// Get where to send the next lookup's queries, rotating the first server if so configured.
+    pub(crate) fn upstream(&self) -> Upstream {
+        Upstream {
+            servers: self.name_servers.clone(),
+            first: if self.rotate {
+                self.next_server.fetch_add(1, Ordering::Relaxed)
+            } else {
+                0
+            },
+            attempts: self.attempts,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Get the EDNS0 UDP payload size to advertise, if any.
+    pub(crate) fn edns(&self) -> Option<(u16, bool)> {
+        if self.edns_payload_size == 0 {
+            None
+        } else {
+            Some((self.edns_payload_size, false))
+        }
+    }
+}
+
+impl Resolver for DnsResolver {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+    }
+
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        let upstream = self.upstream();
+        let edns = self.edns();
+        let mut answered_by = None;
+        let lookup = lookup_following(fqdn, |name| {
+            let (server, transport, response) = upstream.query_existing(name, wire::TYPE_TXT, wire::FLAG_RD | wire::FLAG_AD, edns)?;
+            answered_by = Some((server, transport));
+            Ok(response)
+        })?;
+        answered(fqdn, lookup, answered_by)
+    }
+}
+
+/// Finish a lookup for the specified name, which the last response came from `answered_by`, failing if it found nothing.
+pub(crate) fn answered(fqdn: &str, mut lookup: Lookup, answered_by: Option<(SocketAddr, Transport)>) -> Result<Lookup, Error> {
+    if lookup.records.is_empty() {
+        return Err(Error::NoTxtData(fqdn.to_string()));
+    }
+    lookup.name_server = answered_by.map(|(server, _)| server);
+    lookup.transport = answered_by.map(|(_, transport)| transport);
+    Ok(lookup)
+}
+
+
+/// Look up the TXT records for the specified name by asking `query` for TXT records, following CNAMEs and DNAMEs.
+///
+/// If a response redirects elsewhere without the records there, those are asked for, too.
+/// The AD bit is only reported if all responses had it.
+pub(crate) fn lookup_following<Q: FnMut(&str) -> Result<Message, Error>>(fqdn: &str, mut query: Q) -> Result<Lookup, Error> {
+    let mut following = Following::new(fqdn);
+    loop {
+        let response = query(following.name())?;
+        if let Some(lookup) = following.feed(&response)? {
+            return Ok(lookup);
+        }
+    }
+}
+
+
+/// [`lookup_following()`](fn.lookup_following.html), one response at a time, for when they don't come from a closure.
+#[derive(Debug, Clone)]
+pub(crate) struct Following {
+    fqdn: String,
+    start: String,
+    name: String,
+    cname_chain: Vec<String>,
+    /// Owner and type of each CNAME or DNAME RRset followed, in order.
+    links: Vec<(String, u16)>,
+    cname_ttl: Option<u32>,
+    authenticated_data: bool,
+}
+
+impl Following {
+    pub fn new(fqdn: &str) -> Following {
+        let start = fqdn.to_lowercase();
+        Following {
+            fqdn: fqdn.to_string(),
+            name: start.clone(),
+            start,
+            cname_chain: vec![],
+            links: vec![],
+            cname_ttl: None,
+            authenticated_data: true,
+        }
+    }
+
+    /// Get the name to ask for TXT records next.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the owner and type of each CNAME or DNAME RRset followed so far, to authenticate them with.
+    pub fn links(&self) -> &[(String, u16)] {
+        &self.links
+    }
+
+    /// Take in the response for [`name()`](#method.name), returning the lookup if it's done,
+    /// or `None` if the records need to be asked for at the new name.
+    pub fn feed(&mut self, response: &Message) -> Result<Option<Lookup>, Error> {
+        self.authenticated_data &= response.flag(wire::FLAG_AD);
+
+        let asked = self.name.clone();
+        while let Some((alias, target)) = alias_target(response, &self.name) {
+            if target == self.start || self.cname_chain.contains(&target) || self.cname_chain.len() == MAX_CHAIN_LENGTH {
+                return Err(Error::CnameChain(self.fqdn.clone()));
+            }
+            self.links.push((alias.name.clone(), alias.rtype));
+            self.cname_ttl = Some(self.cname_ttl.map_or(alias.ttl, |ttl| ttl.min(alias.ttl)));
+            self.cname_chain.push(target.clone());
+            self.name = target;
+        }
+
+        let records: Vec<_> = response.answers
+            .iter()
+            .filter(|r| r.rtype == wire::TYPE_TXT && r.class == wire::CLASS_IN && r.name == self.name)
+            .map(|r| {
+                TxtRecord {
+                    data: wire::txt_data(&r.data),
+                    ttl: Some(r.ttl),
+                }
+            })
+            .collect();
+        if records.is_empty() && self.name != asked {
+            return Ok(None);
+        }
+
+        let mut lookup = Lookup::new(self.fqdn.clone(), records);
+        lookup.authenticated_data = self.authenticated_data;
+        lookup.cname_chain = self.cname_chain.clone();
+        lookup.cname_ttl = self.cname_ttl;
+        Ok(Some(lookup))
+    }
+}
+
+/// Get the CNAME or DNAME in the response's answers redirecting the specified name, and where to, if anywhere.
+///
+/// DNAMEs come first, since the CNAMEs name servers synthesise from them aren't signed.
+fn alias_target<'r>(response: &'r Message, name: &str) -> Option<(&'r Record, String)> {
+    // DNAMEs redirect everything under their owner, but not the owner itself
+    let dname = response.answers.iter().find(|r| r.rtype == wire::TYPE_DNAME && r.name != name && r.name != "." && wire::is_subdomain(name, &r.name));
+    if let Some(dname) = dname {
+        if let Ok((target, _)) = wire::read_wire_name(&dname.data) {
+            let prefix = &name[..name.len() - dname.name.len()];
+            let target = if target == "." {
+                prefix.to_string()
+            } else {
+                format!("{}{}", prefix, target)
+            };
+
+            if wire::name_to_wire(&target).is_ok() {
+                return Some((dname, target));
+            }
+        }
+    }
+
+    let cname = response.answers.iter().find(|r| r.rtype == wire::TYPE_CNAME && r.name == name)?;
+    wire::read_wire_name(&cname.data).ok().map(|(target, _)| (cname, target))
+}
@@ -1,8 +1,5 @@
-use self::super::{CryptoAddress, Error, alias_to_fqdn};
-use resolve::{DnsResolver, DnsConfig, default_config};
+use self::super::{CryptoAddress, DnsResolver, Resolver, Lookup, Error, alias_to_fqdn};
 use std::iter::FromIterator;
-use std::time::Duration;
-use resolve::record;
 
 
 /// Ask a DNS server for addresses for the specified OpenAlias.
@@ -44,7 +41,14 @@ use resolve::record;
 ///                 }]);
 /// ```
 pub fn addresses(address: &str) -> Result<Vec<CryptoAddress>, Error> {
-    Ok(Result::from_iter(address_strings(address)?.into_iter().map(|s| s.parse()))?)
+    addresses_with(&DnsResolver::new()?, address)
+}
+
+/// Ask the specified resolver for addresses for the specified OpenAlias.
+///
+/// See [`addresses()`](fn.addresses.html) and [`Resolver`](trait.Resolver.html).
+pub fn addresses_with<R: Resolver + ?Sized>(resolver: &R, address: &str) -> Result<Vec<CryptoAddress>, Error> {
+    Ok(Result::from_iter(address_strings_with(resolver, address)?.into_iter().map(|s| s.parse()))?)
 }
 
 /// Ask a DNS server for "oa1:"-prefixed TXT records for the specified OpenAlias.
@@ -65,21 +69,137 @@ pub fn addresses(address: &str) -> Result<Vec<CryptoAddress>, Error> {
 ///                  tx_description=Donation to Monero Core Team;".to_string()]);
 /// ```
 pub fn address_strings(address: &str) -> Result<Vec<String>, Error> {
-    Ok(Result::from_iter(DnsResolver::new(default_config().unwrap_or_else(|_| {
-            DnsConfig {
-                name_servers: vec!["8.8.8.8:53".parse().unwrap(), "8.8.4.4:53".parse().unwrap()],
-                search: vec![],
-                n_dots: 0,
-                timeout: Duration::from_secs(5),
-                attempts: 5,
-                rotate: true,
-                use_inet6: false,
-            }
-        }))
-        ?
-        .resolve_record::<record::Txt>(&alias_to_fqdn(address).ok_or(Error::AddressParse)?)?
+    address_strings_with(&DnsResolver::new()?, address)
+}
+
+/// Ask the specified resolver for "oa1:"-prefixed TXT records for the specified OpenAlias.
+///
+/// See [`address_strings()`](fn.address_strings.html) and [`Resolver`](trait.Resolver.html).
+pub fn address_strings_with<R: Resolver + ?Sized>(resolver: &R, address: &str) -> Result<Vec<String>, Error> {
+    lookup_with(resolver, address)?.address_strings()
+}
+
+/// Ask a DNS server for TXT records for the specified OpenAlias, reporting everything learned in the process.
+///
+/// See [`Lookup`](struct.Lookup.html).
+pub fn lookup(address: &str) -> Result<Lookup, Error> {
+    lookup_with(&DnsResolver::new()?, address)
+}
+
+/// Ask the specified resolver for TXT records for the specified OpenAlias, reporting everything learned in the process.
+///
+/// See [`Lookup`](struct.Lookup.html).
+///
+/// # Examples
+///
+/// Seeing where an alias really points, with a stand-in DNS-over-HTTPS server:
+///
+/// ```
+/// # use openalias::{DohResolver, DohFormat, Error, lookup_with};
+/// # use std::io::{BufRead, BufReader, Write};
+/// # use std::net::TcpListener;
+/// # use std::thread;
+/// let server = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let url = format!("http://{}/resolve", server.local_addr().unwrap());
+///
+/// thread::spawn(move || {
+///     for conn in server.incoming() {
+///         let conn = conn.unwrap();
+///         let mut request_line = String::new();
+///         BufReader::new(&conn).read_line(&mut request_line).unwrap();
+///
+///         let answers = if request_line.contains("name=donate.example.com.&") {
+///             // Hosted elsewhere, and the server didn't follow
+///             r#"{"name": "donate.example.com.", "type": 5, "TTL": 60, "data": "donate.hostingco.net."}"#
+///         } else if request_line.contains("name=donate.hostingco.net.&") {
+///             // Whose whole zone was moved
+///             r#"{"name": "hostingco.net.", "type": 39, "TTL": 60, "data": "oa.hosting.example."},
+///                {"name": "donate.oa.hosting.example.", "type": 16, "TTL": 60, "data": "oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;"}"#
+///         } else {
+///             r#"{"name": "loop.example.com.", "type": 5, "TTL": 60, "data": "loop.example.com."}"#
+///         };
+///         let name = &request_line[request_line.find("name=").unwrap() + 5..request_line.find("&").unwrap()];
+///         let body = format!(r#"{{"Status": 0, "Question": [{{"name": "{}", "type": 16}}], "Answer": [{}]}}"#, name, answers);
+///         write!(&conn, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+///     }
+/// });
+///
+/// let resolver = DohResolver::new(&url).unwrap().format(DohFormat::Json);
+/// let lookup = lookup_with(&resolver, "donate@example.com").unwrap();
+/// assert_eq!(lookup.cname_chain, vec!["donate.hostingco.net.".to_string(), "donate.oa.hosting.example.".to_string()]);
+/// assert_eq!(lookup.address_strings().unwrap(), vec!["oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;".to_string()]);
+///
+/// match lookup_with(&resolver, "loop@example.com") {
+///     Err(Error::CnameChain(fqdn)) => assert_eq!(fqdn, "loop.example.com."),
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn lookup_with<R: Resolver + ?Sized>(resolver: &R, address: &str) -> Result<Lookup, Error> {
+    resolver.lookup(&alias_to_fqdn(address).ok_or(Error::AddressParse)?)
+}
+
+/// Ask a DNS server for all "oa1:"-prefixed TXT records for the specified OpenAlias, and try to parse each one.
+///
+/// Unlike [`addresses()`](fn.addresses.html), a malformed record doesn't fail the whole lookup.
+pub fn addresses_lenient(address: &str) -> Result<Vec<ParsedRecord>, Error> {
+    addresses_lenient_with(&DnsResolver::new()?, address)
+}
+
+/// Ask the specified resolver for all "oa1:"-prefixed TXT records for the specified OpenAlias, and try to parse each one.
+///
+/// Unlike [`addresses_with()`](fn.addresses_with.html), a malformed record doesn't fail the whole lookup.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{Error, addresses_lenient_with};
+/// let fake = |_: &str| -> Result<Vec<Vec<u8>>, Error> {
+///     Ok(vec![b"oa1:eth recipient_address=0xCAFE".to_vec(),
+///             b"oa1:xmr recipient_address=46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em;"
+///                 .to_vec()])
+/// };
+///
+/// let records = addresses_lenient_with(&fake, "donate@example.com").unwrap();
+/// assert_eq!(records.len(), 2);
+///
+/// assert_eq!(records[0].text, "oa1:eth recipient_address=0xCAFE");
+/// assert!(records[0].address.is_err());
+///
+/// assert_eq!(records[1].address.as_ref().unwrap().cryptocurrency, "xmr");
+/// ```
+pub fn addresses_lenient_with<R: Resolver + ?Sized>(resolver: &R, address: &str) -> Result<Vec<ParsedRecord>, Error> {
+    Ok(oa1_records(resolver, address)?
         .into_iter()
-        .map(|r| r.data)
-        .filter(|s| s.starts_with(b"oa1:"))
-        .map(String::from_utf8))?)
+        .map(|data| {
+            match String::from_utf8(data) {
+                Ok(text) => {
+                    ParsedRecord {
+                        address: text.parse().map_err(Error::from),
+                        text,
+                    }
+                }
+                Err(err) => {
+                    ParsedRecord {
+                        text: String::from_utf8_lossy(err.as_bytes()).into_owned(),
+                        address: Err(err.into()),
+                    }
+                }
+            }
+        })
+        .collect())
+}
+
+
+/// An "oa1:"-prefixed record, alongside the result of parsing it.
+#[derive(Debug)]
+pub struct ParsedRecord {
+    /// The record text, with invalid UTF-8 replaced.
+    pub text: String,
+    /// The parsed address, or why the record couldn't be parsed.
+    pub address: Result<CryptoAddress, Error>,
+}
+
+
+fn oa1_records<R: Resolver + ?Sized>(resolver: &R, address: &str) -> Result<Vec<Vec<u8>>, Error> {
+    Ok(lookup_with(resolver, address)?.records.into_iter().map(|r| r.data).filter(|d| d.starts_with(b"oa1:")).collect())
 }
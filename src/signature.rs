@@ -0,0 +1,209 @@
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use base64::engine::general_purpose::STANDARD;
+use self::super::validation::keccak256;
+use curve25519_dalek::traits::IsIdentity;
+use ripemd::{Digest, Ripemd160};
+use curve25519_dalek::Scalar;
+use self::super::MoneroAddress;
+use base64::Engine;
+use base58_monero;
+use ring::digest;
+
+
+/// What [`CryptoAddress::verify_signature()`](struct.CryptoAddress.html#method.verify_signature) thinks of a record's
+/// `address_signature`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SignatureValidity {
+    /// The signature is by the recipient address' key, over the alias.
+    Valid,
+    /// The signature is not by the recipient address' key, or is malformed, for the specified reason.
+    Invalid(String),
+    /// There's no signature, or no way to check it for the record's cryptocurrency or address type.
+    Unsupported,
+}
+
+impl SignatureValidity {
+    /// Check whether the signature is known to be valid.
+    pub fn is_valid(&self) -> bool {
+        *self == SignatureValidity::Valid
+    }
+}
+
+
+/// Check a base64-encoded Bitcoin signed message, as made by Bitcoin Core's `signmessage`, or its BIP 137 SegWit
+/// extension, by the specified P2PKH, P2SH-P2WPKH, or P2WPKH address.
+pub(crate) fn bitcoin(address: &str, signature: &str, message: &str) -> SignatureValidity {
+    let expected_hash = match bitcoin_address_key_hash(address) {
+        Some(hash) => hash,
+        None => return SignatureValidity::Unsupported,
+    };
+
+    let signature = match STANDARD.decode(signature) {
+        Ok(signature) => signature,
+        Err(e) => return SignatureValidity::Invalid(format!("not base64: {}", e)),
+    };
+    if signature.len() != 65 {
+        return SignatureValidity::Invalid(format!("{} bytes long, instead of 65", signature.len()));
+    }
+    if !(27..=42).contains(&signature[0]) {
+        return SignatureValidity::Invalid(format!("unknown header byte {}", signature[0]));
+    }
+    let compressed = signature[0] >= 31;
+    let mut recovery_id = (signature[0] - 27) % 4;
+
+    let mut ecdsa_signature = match Signature::from_slice(&signature[1..]) {
+        Ok(ecdsa_signature) => ecdsa_signature,
+        Err(_) => return SignatureValidity::Invalid("r or s out of range".to_string()),
+    };
+    if let Some(normalised) = ecdsa_signature.normalize_s() {
+        ecdsa_signature = normalised;
+        recovery_id ^= 1;
+    }
+
+    let key = match VerifyingKey::recover_from_prehash(&bitcoin_message_hash(message),
+                                                       &ecdsa_signature,
+                                                       RecoveryId::from_byte(recovery_id).expect("recovery ID under 4")) {
+        Ok(key) => key,
+        Err(_) => return SignatureValidity::Invalid("no key recoverable".to_string()),
+    };
+    let key = key.to_encoded_point(compressed);
+
+    let matches = match expected_hash {
+        KeyHash::P2pkh(hash) => hash160(key.as_bytes()) == hash,
+        KeyHash::P2wpkh(hash) => compressed && hash160(key.as_bytes()) == hash,
+        KeyHash::P2shP2wpkh(hash) => {
+            let mut redeem_script = vec![0x00, 0x14];
+            redeem_script.extend_from_slice(&hash160(key.as_bytes()));
+            compressed && hash160(&redeem_script) == hash
+        }
+    };
+    if matches {
+        SignatureValidity::Valid
+    } else {
+        SignatureValidity::Invalid("signed by another key".to_string())
+    }
+}
+
+/// Check a Monero message signature, as made by `monero-wallet-cli`'s `sign`, in the "SigV1" or "SigV2" format,
+/// by the specified address' spend key.
+pub(crate) fn monero(address: &MoneroAddress, signature: &str, message: &str) -> SignatureValidity {
+    let (hash, signature) = if let Some(signature) = signature.strip_prefix("SigV1") {
+        (keccak256(message.as_bytes()), signature)
+    } else if let Some(signature) = signature.strip_prefix("SigV2") {
+        (monero_message_hash(address, message), signature)
+    } else {
+        return SignatureValidity::Invalid("no SigV1 or SigV2 prefix".to_string());
+    };
+
+    let signature = match base58_monero::decode(signature) {
+        Ok(signature) => signature,
+        Err(e) => return SignatureValidity::Invalid(format!("not Monero base58: {}", e)),
+    };
+    if signature.len() != 64 {
+        return SignatureValidity::Invalid(format!("{} bytes long, instead of 64", signature.len()));
+    }
+    let (c, r) = match (monero_scalar(&signature[..32]), monero_scalar(&signature[32..])) {
+        (Some(c), Some(r)) if c != Scalar::ZERO => (c, r),
+        _ => return SignatureValidity::Invalid("c or r out of range".to_string()),
+    };
+
+    let key = match CompressedEdwardsY(address.public_spend_key).decompress() {
+        Some(key) => key,
+        None => return SignatureValidity::Invalid("spend key not a curve point".to_string()),
+    };
+    let commitment = EdwardsPoint::vartime_double_scalar_mul_basepoint(&c, &key, &r);
+    if commitment.is_identity() {
+        return SignatureValidity::Invalid("commitment is the identity".to_string());
+    }
+
+    let mut data = hash.to_vec();
+    data.extend_from_slice(&address.public_spend_key);
+    data.extend_from_slice(commitment.compress().as_bytes());
+    if Scalar::from_bytes_mod_order(keccak256(&data)) == c {
+        SignatureValidity::Valid
+    } else {
+        SignatureValidity::Invalid("signed by another key".to_string())
+    }
+}
+
+
+/// The 20-byte hash a Bitcoin address pays to.
+enum KeyHash {
+    /// P2PKH, of the public key.
+    P2pkh([u8; 20]),
+    /// P2WPKH, of the compressed public key.
+    P2wpkh([u8; 20]),
+    /// P2SH, assumed to be of a P2WPKH redeem script.
+    P2shP2wpkh([u8; 20]),
+}
+
+fn bitcoin_address_key_hash(address: &str) -> Option<KeyHash> {
+    let mut hash = [0; 20];
+    if let Ok((hrp, version, program)) = bech32::segwit::decode(address) {
+        // "bc" or "tb", not another coin's
+        if !hrp.is_valid_segwit() || version != bech32::segwit::VERSION_0 || program.len() != 20 {
+            return None;
+        }
+        hash.copy_from_slice(&program);
+        return Some(KeyHash::P2wpkh(hash));
+    }
+
+    let data = bs58::decode(address).into_vec().ok()?;
+    if data.len() != 25 {
+        return None;
+    }
+    hash.copy_from_slice(&data[1..21]);
+    match data[0] {
+        0x00 => Some(KeyHash::P2pkh(hash)),
+        0x05 => Some(KeyHash::P2shP2wpkh(hash)),
+        _ => None,
+    }
+}
+
+/// SHA-256d of the message, prefixed with "Bitcoin Signed Message:\n", both with their lengths.
+fn bitcoin_message_hash(message: &str) -> Vec<u8> {
+    const MAGIC: &[u8] = b"Bitcoin Signed Message:\n";
+
+    let mut data = vec![MAGIC.len() as u8];
+    data.extend_from_slice(MAGIC);
+    if message.len() < 0xFD {
+        data.push(message.len() as u8);
+    } else {
+        data.push(0xFD);
+        data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+    }
+    data.extend_from_slice(message.as_bytes());
+
+    digest::digest(&digest::SHA256, digest::digest(&digest::SHA256, &data).as_ref()).as_ref().to_vec()
+}
+
+/// RIPEMD-160 of SHA-256.
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(digest::digest(&digest::SHA256, data).as_ref()).into()
+}
+
+/// Keccak-256 of the "SigV2" domain separator, the address' keys, the signing mode (0, spend key), and the message with
+/// its length.
+fn monero_message_hash(address: &MoneroAddress, message: &str) -> [u8; 32] {
+    let mut data = b"MoneroMessageSignature\0".to_vec();
+    data.extend_from_slice(&address.public_spend_key);
+    data.extend_from_slice(&address.public_view_key);
+    data.push(0);
+    let mut len = message.len();
+    while len >= 0x80 {
+        data.push((len as u8 & 0x7F) | 0x80);
+        len >>= 7;
+    }
+    data.push(len as u8);
+    data.extend_from_slice(message.as_bytes());
+
+    keccak256(&data)
+}
+
+/// A canonical (fully reduced) little-endian scalar.
+fn monero_scalar(bytes: &[u8]) -> Option<Scalar> {
+    let mut scalar = [0; 32];
+    scalar.copy_from_slice(bytes);
+    Scalar::from_canonical_bytes(scalar).into()
+}
@@ -0,0 +1,95 @@
+//! TLS connections for the encrypted DNS transports.
+
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme, StreamOwned};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::crypto::{self, WebPkiSupportedAlgorithms};
+use rustls::server::ParsedCertificate;
+use std::net::{SocketAddr, TcpStream};
+use std::io::{self, ErrorKind};
+use std::convert::TryFrom;
+use std::time::Duration;
+use ring::digest;
+use std::sync::Arc;
+
+
+pub type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+
+/// Client settings trusting Mozilla's root certificates.
+pub fn client_config() -> Arc<ClientConfig> {
+    let roots = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    Arc::new(ClientConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
+/// Client settings trusting only servers whose (end-entity) certificate's key has one of the specified SPKI SHA-256 hashes.
+pub fn pinned_client_config(pins: Vec<[u8; 32]>) -> Arc<ClientConfig> {
+    let provider = crypto::ring::default_provider();
+    let verifier = PinVerifier {
+        pins,
+        algorithms: provider.signature_verification_algorithms,
+    };
+    Arc::new(ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
+
+/// Connect to the specified server, expecting a certificate for `host`.
+///
+/// The handshake happens on first read or write.
+pub fn connect(config: &Arc<ClientConfig>, host: &str, server: &SocketAddr, timeout: Duration) -> io::Result<TlsStream> {
+    let name = ServerName::try_from(host.to_string()).map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+    let conn = ClientConnection::new(config.clone(), name).map_err(io::Error::other)?;
+    Ok(StreamOwned::new(conn, tcp_connect(server, timeout)?))
+}
+
+/// Connect to the specified server, with all operations timing out after `timeout`.
+pub fn tcp_connect(server: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+    let sock = TcpStream::connect_timeout(server, timeout)?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.set_write_timeout(Some(timeout))?;
+    Ok(sock)
+}
+
+
+/// Authenticates the server by its key alone, as in RFC 7858's out-of-band key-pinned privacy profile.
+#[derive(Debug)]
+struct PinVerifier {
+    pins: Vec<[u8; 32]>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(&self, end_entity: &CertificateDer, _: &[CertificateDer], _: &ServerName, _: &[u8], _: UnixTime)
+                          -> Result<ServerCertVerified, rustls::Error> {
+        // Only the end entity's key is proven to be the server's by the handshake signature,
+        // anyone can send along the pinned certificate as an intermediate
+        let spki = ParsedCertificate::try_from(end_entity)?.subject_public_key_info();
+        let hash = digest::digest(&digest::SHA256, spki.as_ref());
+        if self.pins.iter().any(|pin| pin[..] == *hash.as_ref()) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General("server key doesn't match the pinned SPKI hashes".to_string()))
+        }
+    }
+
+    fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
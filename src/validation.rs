@@ -0,0 +1,222 @@
+use self::super::{CryptoAddress, MoneroAddress, MoneroNetwork};
+use std::collections::BTreeMap;
+use tiny_keccak::{Hasher, Keccak};
+use ring::digest;
+use std::fmt;
+
+
+/// A check whether an address is valid for one cryptocurrency.
+///
+/// Closures of the right signature are validators, too.
+pub trait AddressValidator {
+    /// Check the specified address, returning why it's invalid, if it is.
+    fn validate(&self, address: &str) -> Result<(), String>;
+}
+
+impl<F: Fn(&str) -> Result<(), String>> AddressValidator for F {
+    fn validate(&self, address: &str) -> Result<(), String> {
+        self(address)
+    }
+}
+
+
+/// What an [`AddressValidators`](struct.AddressValidators.html) registry thinks of an address.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AddressValidity {
+    /// The address is valid for its cryptocurrency.
+    Valid,
+    /// The address is not valid for its cryptocurrency, for the specified reason.
+    Invalid(String),
+    /// There's no validator for the address' cryptocurrency.
+    Unknown,
+}
+
+impl AddressValidity {
+    /// Check whether the address is known to be valid.
+    pub fn is_valid(&self) -> bool {
+        *self == AddressValidity::Valid
+    }
+}
+
+
+/// Address validators by cryptocurrency ticker, so a typo'd or wrong-network address can be caught before paying it.
+///
+/// Built-in validators, all for the main networks:
+///
+/// | Ticker | Checks                                                                               |
+/// |--------|--------------------------------------------------------------------------------------|
+/// | btc    | Base58Check P2PKH and P2SH, bech32 (SegWit v0) and bech32m (v1+) with the "bc" HRP   |
+/// | ltc    | Base58Check P2PKH and P2SH, bech32 and bech32m with the "ltc" HRP                    |
+/// | doge   | Base58Check P2PKH and P2SH                                                           |
+/// | xmr    | Monero base58, Keccak-256 checksum, and standard, integrated, or subaddress network byte |
+/// | eth    | 20 hex bytes, with the EIP-55 checksum if mixed-case                                 |
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{AddressValidators, AddressValidity, CryptoAddress};
+/// let validators = AddressValidators::new();
+///
+/// let address: CryptoAddress = "oa1:btc recipient_address=1MoSyGZp3SKpoiXPXfZDFK7cDUFCVtEDeS;".parse().unwrap();
+/// assert_eq!(validators.validate(&address), AddressValidity::Valid);
+///
+/// // Typo'd
+/// assert!(!validators.validate_address("btc", "1MoSyGZp3SKpoiXPXfZDFK7cDUFCVtEDeT").is_valid());
+/// // Testnet
+/// assert_eq!(validators.validate_address("btc", "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"),
+///            AddressValidity::Invalid("for the tb network".to_string()));
+/// assert!(validators.validate_address("BTC", "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").is_valid());
+/// assert!(validators.validate_address("btc", "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0").is_valid());
+///
+/// assert!(validators.validate_address("ltc", "LUEweDxDA4WhvWiNXXSxjM9CYzHPJv4QQF").is_valid());
+/// assert_eq!(validators.validate_address("btc", "LUEweDxDA4WhvWiNXXSxjM9CYzHPJv4QQF"),
+///            AddressValidity::Invalid("version byte 0x30 is for another network or cryptocurrency".to_string()));
+/// assert!(validators.validate_address("doge", "DEA5vGb2NpAwCiCp5yTE16F3DueQUVivQp").is_valid());
+///
+/// assert!(validators.validate_address("xmr", "46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em").is_valid());
+/// assert!(!validators.validate_address("xmr", "46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16en").is_valid());
+///
+/// assert!(validators.validate_address("eth", "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_valid());
+/// assert!(!validators.validate_address("eth", "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_valid());
+///
+/// assert_eq!(validators.validate_address("zec", "t1Ku"), AddressValidity::Unknown);
+/// ```
+///
+/// Add a validator:
+///
+/// ```
+/// # use openalias::{AddressValidators, AddressValidity};
+/// let validators = AddressValidators::new().register("nano", |address: &str| if address.starts_with("nano_") {
+///     Ok(())
+/// } else {
+///     Err("no nano_ prefix".to_string())
+/// });
+/// assert_eq!(validators.validate_address("nano", "xrb_1111"), AddressValidity::Invalid("no nano_ prefix".to_string()));
+/// ```
+pub struct AddressValidators {
+    validators: BTreeMap<String, Box<dyn AddressValidator + Send + Sync>>,
+}
+
+impl AddressValidators {
+    /// Create a registry with the built-in validators.
+    pub fn new() -> AddressValidators {
+        AddressValidators::empty()
+            .register("btc", |address: &str| base58check_or_segwit(address, &[0x00, 0x05], "bc"))
+            .register("ltc", |address: &str| base58check_or_segwit(address, &[0x30, 0x32, 0x05], "ltc"))
+            .register("doge", |address: &str| base58check(address, &[0x1E, 0x16]))
+            .register("xmr", monero)
+            .register("eth", ethereum)
+    }
+
+    /// Create a registry with no validators.
+    pub fn empty() -> AddressValidators {
+        AddressValidators { validators: BTreeMap::new() }
+    }
+
+    /// Validate addresses for the specified cryptocurrency with the specified validator, instead of any previous one.
+    pub fn register<V: AddressValidator + Send + Sync + 'static>(mut self, cryptocurrency: &str, validator: V) -> AddressValidators {
+        self.validators.insert(cryptocurrency.to_lowercase(), Box::new(validator));
+        self
+    }
+
+    /// Get the cryptocurrencies with validators.
+    pub fn cryptocurrencies(&self) -> Vec<&str> {
+        self.validators.keys().map(|k| &k[..]).collect()
+    }
+
+    /// Check the address in the specified record.
+    pub fn validate(&self, address: &CryptoAddress) -> AddressValidity {
+        self.validate_address(&address.cryptocurrency, &address.address)
+    }
+
+    /// Check the specified address for the specified cryptocurrency.
+    pub fn validate_address(&self, cryptocurrency: &str, address: &str) -> AddressValidity {
+        match self.validators.get(&cryptocurrency.to_lowercase()) {
+            Some(validator) => {
+                match validator.validate(address) {
+                    Ok(()) => AddressValidity::Valid,
+                    Err(why) => AddressValidity::Invalid(why),
+                }
+            }
+            None => AddressValidity::Unknown,
+        }
+    }
+}
+
+impl Default for AddressValidators {
+    fn default() -> AddressValidators {
+        AddressValidators::new()
+    }
+}
+
+impl fmt::Debug for AddressValidators {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AddressValidators").field("cryptocurrencies", &self.cryptocurrencies()).finish()
+    }
+}
+
+
+/// Check a Base58Check-encoded 20-byte hash with one of the specified version bytes.
+fn base58check(address: &str, versions: &[u8]) -> Result<(), String> {
+    let data = bs58::decode(address).into_vec().map_err(|e| format!("not base58: {}", e))?;
+    if data.len() != 25 {
+        return Err(format!("{} bytes long, instead of 25", data.len()));
+    }
+
+    let (payload, checksum) = data.split_at(21);
+    let hash = digest::digest(&digest::SHA256, digest::digest(&digest::SHA256, payload).as_ref());
+    if hash.as_ref()[..4] != *checksum {
+        return Err("checksum mismatch".to_string());
+    }
+    if !versions.contains(&payload[0]) {
+        return Err(format!("version byte 0x{:02X} is for another network or cryptocurrency", payload[0]));
+    }
+    Ok(())
+}
+
+/// Check a SegWit address with the specified human-readable part if it looks like one, or a Base58Check one otherwise.
+fn base58check_or_segwit(address: &str, versions: &[u8], hrp: &str) -> Result<(), String> {
+    match bech32::segwit::decode(address) {
+        Ok((found, _, _)) if found.to_lowercase() != hrp => Err(format!("for the {} network", found)),
+        Ok(_) => Ok(()),
+        Err(e) if address.to_lowercase().starts_with(&format!("{}1", hrp)) => Err(format!("not a valid SegWit address: {}", e)),
+        Err(_) => base58check(address, versions),
+    }
+}
+
+/// Check a Monero main network address.
+fn monero(address: &str) -> Result<(), String> {
+    match address.parse::<MoneroAddress>() {
+        Ok(address) if address.network != MoneroNetwork::Mainnet => Err(format!("for the {}", address.network)),
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Check a "0x"-prefixed Ethereum address, and its EIP-55 checksum if mixed-case.
+fn ethereum(address: &str) -> Result<(), String> {
+    let hex = address.strip_prefix("0x").ok_or_else(|| "no 0x prefix".to_string())?;
+    if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("not 40 hexadecimal digits".to_string());
+    }
+    if !hex.bytes().any(|b| b.is_ascii_lowercase()) || !hex.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Ok(());
+    }
+
+    let hash = keccak256(hex.to_lowercase().as_bytes());
+    for (i, b) in hex.bytes().enumerate() {
+        let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0xF;
+        if b.is_ascii_alphabetic() && b.is_ascii_uppercase() != (nibble >= 8) {
+            return Err("EIP-55 checksum mismatch".to_string());
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    hasher.finalize(&mut hash);
+    hash
+}
@@ -0,0 +1,725 @@
+//! Bare-bones DNS wire format handling.
+//!
+//! The `resolve` crate only hands out record data, without the signatures, TTLs and header bits needed to tell whether
+//! an answer can be trusted, so the lookups needing those talk to the name servers directly through this module.
+
+
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use self::super::{Transport, FqdnError, Error};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::cmp::Ordering;
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::Duration;
+use std::mem;
+
+
+pub const TYPE_NS: u16 = 2;
+pub const TYPE_CNAME: u16 = 5;
+pub const TYPE_SOA: u16 = 6;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_MX: u16 = 15;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_DNAME: u16 = 39;
+pub const TYPE_OPT: u16 = 41;
+pub const TYPE_DS: u16 = 43;
+pub const TYPE_RRSIG: u16 = 46;
+pub const TYPE_NSEC: u16 = 47;
+pub const TYPE_DNSKEY: u16 = 48;
+pub const TYPE_NSEC3: u16 = 50;
+
+pub const CLASS_IN: u16 = 1;
+
+pub const FLAG_TC: u16 = 0x0200;
+pub const FLAG_RD: u16 = 0x0100;
+pub const FLAG_AD: u16 = 0x0020;
+pub const FLAG_CD: u16 = 0x0010;
+
+pub const RCODE_FORMERR: u8 = 1;
+pub const RCODE_SERVFAIL: u8 = 2;
+pub const RCODE_NXDOMAIN: u8 = 3;
+pub const RCODE_REFUSED: u8 = 5;
+
+/// EDNS0 header flag requesting DNSSEC records be included in the response.
+pub const EDNS_FLAG_DO: u16 = 0x8000;
+
+
+/// A single resource record, with all domain names lowercased and expanded.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Record {
+    /// Owner name, absolute (i.e. ending with a dot).
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    /// RDATA, with embedded names (for the types we care about) in uncompressed canonical form.
+    pub data: Vec<u8>,
+}
+
+/// A parsed DNS message.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Message {
+    pub id: u16,
+    pub flags: u16,
+    pub questions: Vec<(String, u16, u16)>,
+    pub answers: Vec<Record>,
+    pub authority: Vec<Record>,
+    pub additional: Vec<Record>,
+}
+
+impl Message {
+    /// Get the response code from the header (EDNS-extended bits are ignored).
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0x000F) as u8
+    }
+
+    /// Check whether the specified header flag is set.
+    pub fn flag(&self, flag: u16) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Parse a message from its wire form.
+    pub fn parse(buf: &[u8]) -> io::Result<Message> {
+        let mut rdr = Reader { buf, pos: 0 };
+
+        let id = rdr.u16()?;
+        let flags = rdr.u16()?;
+        let counts = [rdr.u16()?, rdr.u16()?, rdr.u16()?, rdr.u16()?];
+
+        let mut questions = Vec::with_capacity(counts[0] as usize);
+        for _ in 0..counts[0] {
+            questions.push((rdr.name()?, rdr.u16()?, rdr.u16()?));
+        }
+
+        let answers = rdr.records(counts[1])?;
+        let authority = rdr.records(counts[2])?;
+        let additional = rdr.records(counts[3])?;
+
+        Ok(Message {
+            id,
+            flags,
+            questions,
+            answers,
+            authority,
+            additional,
+        })
+    }
+}
+
+
+/// Where and how to send queries.
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    pub servers: Vec<SocketAddr>,
+    /// Index of the server to ask first, wrapped around.
+    pub first: usize,
+    /// How many rounds over the servers to make.
+    pub attempts: u32,
+    pub timeout: Duration,
+}
+
+impl Upstream {
+    /// Ask the servers in turn until one gives a usable (i.e. not SERVFAIL or REFUSED) response.
+    pub fn query(&self, name: &str, rtype: u16, flags: u16, edns: Option<(u16, bool)>) -> Result<(SocketAddr, Transport, Message), Error> {
+        let mut rounds = Rounds::new();
+        loop {
+            let server = rounds.next_server(self)?;
+            let outcome = self.exchange(&server, name, rtype, flags, edns);
+            if let Some((transport, msg)) = rounds.outcome(server, outcome)? {
+                return Ok((server, transport, msg));
+            }
+        }
+    }
+
+    /// Like [`query()`](#method.query), but turn NXDOMAIN and truncated responses into errors.
+    pub fn query_existing(&self, name: &str, rtype: u16, flags: u16, edns: Option<(u16, bool)>)
+                          -> Result<(SocketAddr, Transport, Message), Error> {
+        let (server, transport, response) = self.query(name, rtype, flags, edns)?;
+        check_existing(name, &response)?;
+        Ok((server, transport, response))
+    }
+
+    /// Ask a single server, as the [`Exchange`](struct.Exchange.html) directs.
+    fn exchange(&self, server: &SocketAddr, name: &str, rtype: u16, flags: u16, edns: Option<(u16, bool)>) -> Result<(Transport, Message), Error> {
+        let mut exchange = Exchange::new(name, rtype, flags, edns)?;
+        loop {
+            match exchange.udp_response(&exchange_udp(server, exchange.query(), self.timeout).map_err(io_error)?)? {
+                Step::Done(response) => return Ok((Transport::Udp, response)),
+                Step::Retry => {}
+                Step::Tcp => return Ok((Transport::Tcp, Message::parse(&exchange_tcp(server, exchange.query(), self.timeout).map_err(io_error)?)?)),
+            }
+        }
+    }
+}
+
+
+/// Going over the servers in turn until one gives a usable response, without doing the asking,
+/// so blocking and non-blocking lookups pick servers and give up alike.
+#[derive(Debug)]
+pub struct Rounds {
+    /// How many servers were asked so far.
+    tries: usize,
+    last_err: Error,
+}
+
+impl Rounds {
+    pub fn new() -> Rounds {
+        Rounds {
+            tries: 0,
+            last_err: no_name_servers(),
+        }
+    }
+
+    /// Get the server to ask next, or why the last one failed if all have been asked enough.
+    pub fn next_server(&mut self, upstream: &Upstream) -> Result<SocketAddr, Error> {
+        if self.tries == upstream.servers.len() * upstream.attempts.max(1) as usize {
+            return Err(mem::replace(&mut self.last_err, no_name_servers()));
+        }
+        let server = upstream.servers[(upstream.first + self.tries) % upstream.servers.len()];
+        self.tries += 1;
+        Ok(server)
+    }
+
+    /// Take in what asking the specified server came to, returning the response if it's usable,
+    /// or `None` if the next server should be asked.
+    pub fn outcome(&mut self, server: SocketAddr, outcome: Result<(Transport, Message), Error>) -> Result<Option<(Transport, Message)>, Error> {
+        match outcome {
+            Ok((_, ref msg)) if msg.rcode() == RCODE_SERVFAIL => self.last_err = Error::ServerFailure(server.to_string()),
+            Ok((_, ref msg)) if msg.rcode() == RCODE_REFUSED => self.last_err = Error::Refused(server.to_string()),
+            Ok(response) => return Ok(Some(response)),
+            Err(err @ Error::FqdnParse(_)) => return Err(err),
+            Err(err) => self.last_err = err,
+        }
+        Ok(None)
+    }
+}
+
+fn no_name_servers() -> Error {
+    Error::Io(io::Error::new(ErrorKind::InvalidInput, "no name servers configured"))
+}
+
+/// Turn NXDOMAIN and truncated responses for the specified name into errors.
+pub fn check_existing(name: &str, response: &Message) -> Result<(), Error> {
+    if response.rcode() == RCODE_NXDOMAIN {
+        return Err(Error::NxDomain(name.to_string()));
+    }
+    if response.flag(FLAG_TC) {
+        return Err(Error::Truncated(name.to_string()));
+    }
+    Ok(())
+}
+
+
+/// Asking a single server, without doing the sending and receiving:
+/// over UDP, again without EDNS0 if it doesn't understand it, and over TCP if the response didn't fit.
+#[derive(Debug)]
+pub struct Exchange {
+    name: String,
+    rtype: u16,
+    flags: u16,
+    edns: Option<(u16, bool)>,
+    query: Vec<u8>,
+}
+
+/// What to do after a UDP response.
+#[derive(Debug)]
+pub enum Step {
+    /// Send the new [`query()`](struct.Exchange.html#method.query) over UDP.
+    Retry,
+    /// Send the query over TCP, and take that response.
+    Tcp,
+    /// Take this response.
+    Done(Message),
+}
+
+impl Exchange {
+    pub fn new(name: &str, rtype: u16, flags: u16, edns: Option<(u16, bool)>) -> Result<Exchange, FqdnError> {
+        Ok(Exchange {
+            name: name.to_string(),
+            rtype,
+            flags,
+            edns,
+            query: build_query(message_id(), name, rtype, flags, edns)?,
+        })
+    }
+
+    /// Get the query to send.
+    pub fn query(&self) -> &[u8] {
+        &self.query
+    }
+
+    /// Take in the UDP response to [`query()`](#method.query), which must be to it.
+    pub fn udp_response(&mut self, response: &[u8]) -> Result<Step, Error> {
+        let response = Message::parse(response)?;
+        if self.edns.is_some() && response.rcode() == RCODE_FORMERR {
+            self.edns = None;
+            self.query = build_query(message_id(), &self.name, self.rtype, self.flags, None)?;
+            Ok(Step::Retry)
+        } else if response.flag(FLAG_TC) {
+            Ok(Step::Tcp)
+        } else {
+            Ok(Step::Done(response))
+        }
+    }
+}
+
+
+/// Build a recursive query for the specified name and type.
+///
+/// `edns` is the UDP payload size to advertise and whether to set the DO bit, if an OPT record is to be included.
+pub fn build_query(id: u16, name: &str, rtype: u16, flags: u16, edns: Option<(u16, bool)>) -> Result<Vec<u8>, FqdnError> {
+    let mut out = Vec::with_capacity(64);
+    push_u16(&mut out, id);
+    push_u16(&mut out, flags);
+    push_u16(&mut out, 1);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, if edns.is_some() { 1 } else { 0 });
+
+    out.extend(name_to_wire(name)?);
+    push_u16(&mut out, rtype);
+    push_u16(&mut out, CLASS_IN);
+
+    if let Some((payload_size, dnssec_ok)) = edns {
+        out.push(0);
+        push_u16(&mut out, TYPE_OPT);
+        push_u16(&mut out, payload_size);
+        out.push(0);
+        out.push(0);
+        push_u16(&mut out, if dnssec_ok { EDNS_FLAG_DO } else { 0 });
+        push_u16(&mut out, 0);
+    }
+
+    Ok(out)
+}
+
+/// Get a fresh, unpredictable message ID.
+pub fn message_id() -> u16 {
+    RandomState::new().build_hasher().finish() as u16
+}
+
+/// Check whether the response is to the query, i.e. has the same ID and question.
+///
+/// Servers may leave the question out of FORMERR responses, as they can't make sense of it.
+pub fn answers_query(query: &[u8], response: &[u8]) -> bool {
+    match (Message::parse(query), Message::parse(response)) {
+        (Ok(query), Ok(response)) => {
+            query.id == response.id && (response.questions == query.questions || (response.questions.is_empty() && response.rcode() == RCODE_FORMERR))
+        }
+        _ => false,
+    }
+}
+
+/// Send a query to the specified server over UDP, and wait for a response to it.
+pub fn exchange_udp(server: &SocketAddr, query: &[u8], timeout: Duration) -> io::Result<Vec<u8>> {
+    let sock = UdpSocket::bind(if server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.send_to(query, server)?;
+
+    let mut buf = [0u8; 65535];
+    loop {
+        let (len, from) = sock.recv_from(&mut buf)?;
+        if from == *server && answers_query(query, &buf[..len]) {
+            return Ok(buf[..len].to_vec());
+        }
+    }
+}
+
+/// Send a query to the specified server over TCP, and read the response, which must be to it.
+pub fn exchange_tcp(server: &SocketAddr, query: &[u8], timeout: Duration) -> io::Result<Vec<u8>> {
+    let conn = TcpStream::connect_timeout(server, timeout)?;
+    conn.set_read_timeout(Some(timeout))?;
+    conn.set_write_timeout(Some(timeout))?;
+    exchange_stream(conn, query)
+}
+
+/// Send a length-prefixed query over the connection, and read the response, which must be to it.
+pub fn exchange_stream<S: Read + Write>(mut conn: S, query: &[u8]) -> io::Result<Vec<u8>> {
+    conn.write_all(&frame(query))?;
+    conn.flush()?;
+
+    let mut len = [0u8; 2];
+    conn.read_exact(&mut len)?;
+    let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+    conn.read_exact(&mut buf)?;
+    expect_answer(query, &buf)?;
+    Ok(buf)
+}
+
+/// Prefix the message with its length, for sending over a stream.
+pub fn frame(msg: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(msg.len() + 2);
+    push_u16(&mut framed, msg.len() as u16);
+    framed.extend_from_slice(msg);
+    framed
+}
+
+/// Fail unless the response is to the query, for where another response can't be waited for instead.
+pub fn expect_answer(query: &[u8], response: &[u8]) -> io::Result<()> {
+    if answers_query(query, response) {
+        Ok(())
+    } else {
+        Err(malformed("response doesn't match query"))
+    }
+}
+
+
+/// Get the text of a TXT record's RDATA, i.e. its character-strings concatenated.
+pub fn txt_data(rdata: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(rdata.len());
+    let mut pos = 0;
+    while pos < rdata.len() {
+        let len = rdata[pos] as usize;
+        out.extend(&rdata[pos + 1..rdata.len().min(pos + 1 + len)]);
+        pos += len + 1;
+    }
+    out
+}
+
+/// Get the RDATA of a TXT record with the specified text, split into character-strings as needed.
+pub fn txt_rdata(text: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len() + text.len() / 255 + 1);
+    for segment in text.chunks(255) {
+        out.push(segment.len() as u8);
+        out.extend_from_slice(segment);
+    }
+    if text.is_empty() {
+        out.push(0);
+    }
+    out
+}
+
+/// Split TXT RDATA in presentation format (whitespace-separated, optionally quoted, `\X`/`\DDD`-escaped) into its
+/// unescaped character-strings.
+pub fn character_strings(text: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut segments = vec![];
+    let mut pos = 0;
+    loop {
+        while pos < text.len() && text[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos == text.len() {
+            return Ok(segments);
+        }
+
+        let quoted = text[pos] == b'"';
+        if quoted {
+            pos += 1;
+        }
+
+        let mut segment = vec![];
+        loop {
+            match text.get(pos).cloned() {
+                None if quoted => return Err("unterminated quoted string".to_string()),
+                None => break,
+                Some(b'"') if quoted => {
+                    pos += 1;
+                    break;
+                }
+                Some(b) if !quoted && b.is_ascii_whitespace() => break,
+                Some(b'\\') => {
+                    let (b, len) = unescape(&text[pos + 1..])?;
+                    segment.push(b);
+                    pos += 1 + len;
+                }
+                Some(b) => {
+                    segment.push(b);
+                    pos += 1;
+                }
+            }
+        }
+        segments.push(segment);
+    }
+}
+
+/// Decode the escape after a `\`, returning the byte and how long the escape was.
+fn unescape(text: &[u8]) -> Result<(u8, usize), String> {
+    match text.first() {
+        None => Err("escape at end of text".to_string()),
+        Some(d) if d.is_ascii_digit() => {
+            if text.len() < 3 || !text[..3].iter().all(|d| d.is_ascii_digit()) {
+                return Err(format!("incomplete \\DDD escape \\{}", String::from_utf8_lossy(&text[..text.len().min(3)])));
+            }
+            let value = text[..3].iter().fold(0u32, |acc, d| acc * 10 + (d - b'0') as u32);
+            if value > 0xFF {
+                return Err(format!("\\{} escape out of range", value));
+            }
+            Ok((value as u8, 3))
+        }
+        Some(&b) => Ok((b, 1)),
+    }
+}
+
+/// Convert a (dot-separated, optionally `\`-escaped) domain name into its lowercased uncompressed wire form.
+///
+/// Names with labels over 63 octets, or over 255 octets in wire form, can't be represented.
+pub fn name_to_wire(name: &str) -> Result<Vec<u8>, FqdnError> {
+    let mut out = Vec::with_capacity(name.len() + 2);
+    for label in labels(name) {
+        if label.len() > 63 {
+            return Err(FqdnError::LabelTooLong(String::from_utf8_lossy(&label).into_owned()));
+        }
+        out.push(label.len() as u8);
+        out.extend(label);
+    }
+    out.push(0);
+
+    if out.len() > 255 {
+        return Err(FqdnError::NameTooLong(out.len() - 2));
+    }
+    Ok(out)
+}
+
+/// Split a (dot-separated, optionally `\`-escaped) domain name into its lowercased labels, not including the root.
+fn labels(name: &str) -> Vec<Vec<u8>> {
+    let mut out = vec![];
+    let mut label = Vec::with_capacity(63);
+    let mut bytes = name.bytes();
+
+    while let Some(b) = bytes.next() {
+        match b {
+            b'.' => {
+                if !label.is_empty() {
+                    out.push(label);
+                    label = Vec::with_capacity(63);
+                }
+            }
+            b'\\' => {
+                match bytes.next() {
+                    Some(d @ b'0'..=b'9') => {
+                        let d1 = bytes.next().unwrap_or(b'0');
+                        let d2 = bytes.next().unwrap_or(b'0');
+                        label.push(((d - b'0') as u32 * 100 + (d1.wrapping_sub(b'0')) as u32 * 10 + (d2.wrapping_sub(b'0')) as u32) as u8);
+                    }
+                    Some(c) => label.push(c.to_ascii_lowercase()),
+                    None => {}
+                }
+            }
+            c => label.push(c.to_ascii_lowercase()),
+        }
+    }
+    if !label.is_empty() {
+        out.push(label);
+    }
+    out
+}
+
+/// Get the amount of labels in a name, not counting the root.
+pub fn label_count(name: &str) -> usize {
+    labels(name).len()
+}
+
+/// Get the parent of the specified absolute name, or `None` for the root.
+pub fn parent_name(name: &str) -> Option<&str> {
+    if name == "." || name.is_empty() {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (i, c) in name.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '.' if !escaped => return Some(if i + 1 == name.len() { "." } else { &name[i + 1..] }),
+            _ => escaped = false,
+        }
+    }
+    Some(".")
+}
+
+/// Check whether `name` is equal to, or below, `zone`.
+pub fn is_subdomain(name: &str, zone: &str) -> bool {
+    let name = labels(name);
+    let zone = labels(zone);
+    name.len() >= zone.len() && name[name.len() - zone.len()..] == zone[..]
+}
+
+/// Compare names in the canonical DNS order, as per RFC 4034 section 6.1, i.e. label by label from the right.
+pub fn canonical_cmp(a: &str, b: &str) -> Ordering {
+    labels(a).iter().rev().cmp(labels(b).iter().rev())
+}
+
+/// Check whether an NSEC or NSEC3 type bit map, as per RFC 4034 section 4.1.2, has the specified type.
+pub fn has_type(bitmaps: &[u8], rtype: u16) -> bool {
+    let mut rest = bitmaps;
+    while rest.len() >= 2 {
+        let (window, len) = (rest[0], rest[1] as usize);
+        let bitmap = match rest.get(2..2 + len) {
+            Some(bitmap) => bitmap,
+            None => return false,
+        };
+        if window as u16 == rtype >> 8 {
+            return bitmap.get((rtype as usize & 0xFF) / 8).is_some_and(|b| b & (0x80 >> (rtype & 0x07)) != 0);
+        }
+        rest = &rest[2 + len..];
+    }
+    false
+}
+
+fn push_u16(out: &mut Vec<u8>, val: u16) {
+    out.push((val >> 8) as u8);
+    out.push(val as u8);
+}
+
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.pos + len > self.buf.len() {
+            return Err(malformed("message truncated"));
+        }
+        let out = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(out)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(((b[0] as u16) << 8) | b[1] as u16)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(((b[0] as u32) << 24) | ((b[1] as u32) << 16) | ((b[2] as u32) << 8) | b[3] as u32)
+    }
+
+    /// Read a possibly-compressed name into uncompressed wire form.
+    fn wire_name(&mut self) -> io::Result<Vec<u8>> {
+        name_to_wire(&self.name()?).map_err(|_| malformed("name too long"))
+    }
+
+    /// Read a possibly-compressed name into presentation form.
+    fn name(&mut self) -> io::Result<String> {
+        let mut out = String::new();
+        let mut pos = self.pos;
+        let mut jumped = false;
+        let mut jumps = 0;
+
+        loop {
+            let len = *self.buf.get(pos).ok_or_else(|| malformed("name truncated"))? as usize;
+            match len & 0xC0 {
+                0x00 if len == 0 => {
+                    if !jumped {
+                        self.pos = pos + 1;
+                    }
+                    break;
+                }
+                0x00 => {
+                    let label = self.buf.get(pos + 1..pos + 1 + len).ok_or_else(|| malformed("label truncated"))?;
+                    for &b in label {
+                        match b.to_ascii_lowercase() {
+                            b'.' | b'\\' => {
+                                out.push('\\');
+                                out.push(b as char);
+                            }
+                            c @ 0x21..=0x7E => out.push(c as char),
+                            c => out.push_str(&format!("\\{:03}", c)),
+                        }
+                    }
+                    out.push('.');
+                    pos += len + 1;
+                }
+                0xC0 => {
+                    let low = *self.buf.get(pos + 1).ok_or_else(|| malformed("pointer truncated"))? as usize;
+                    if !jumped {
+                        self.pos = pos + 2;
+                    }
+                    jumped = true;
+                    jumps += 1;
+                    if jumps > 64 {
+                        return Err(malformed("compression loop"));
+                    }
+                    pos = ((len & 0x3F) << 8) | low;
+                }
+                _ => return Err(malformed("unknown label type")),
+            }
+        }
+
+        if out.is_empty() {
+            out.push('.');
+        }
+        Ok(out)
+    }
+
+    fn records(&mut self, count: u16) -> io::Result<Vec<Record>> {
+        let mut out = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let name = self.name()?;
+            let rtype = self.u16()?;
+            let class = self.u16()?;
+            let ttl = self.u32()?;
+            let len = self.u16()? as usize;
+            let end = self.pos + len;
+            if end > self.buf.len() {
+                return Err(malformed("RDATA truncated"));
+            }
+
+            let data = match rtype {
+                TYPE_NS | TYPE_CNAME | TYPE_PTR | TYPE_DNAME => self.wire_name()?,
+                TYPE_MX => {
+                    let mut data = self.bytes(2)?.to_vec();
+                    data.extend(self.wire_name()?);
+                    data
+                }
+                TYPE_SOA => {
+                    let mut data = self.wire_name()?;
+                    data.extend(self.wire_name()?);
+                    data.extend(self.bytes(20)?);
+                    data
+                }
+                TYPE_RRSIG => {
+                    let mut data = self.bytes(18)?.to_vec();
+                    data.extend(self.wire_name()?);
+                    if self.pos > end {
+                        return Err(malformed("RRSIG truncated"));
+                    }
+                    data.extend(&self.buf[self.pos..end]);
+                    data
+                }
+                _ => self.buf[self.pos..end].to_vec(),
+            };
+            self.pos = end;
+
+            out.push(Record {
+                name,
+                rtype,
+                class,
+                ttl,
+                data,
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// Read a name in uncompressed wire form from the front of `data`, returning it and the remaining data.
+pub fn read_wire_name(data: &[u8]) -> io::Result<(String, &[u8])> {
+    let mut rdr = Reader { buf: data, pos: 0 };
+    let name = rdr.name()?;
+    Ok((name, &data[rdr.pos..]))
+}
+
+/// Turn the response codes meaning the lookup failed into errors, `server` being the one which answered.
+pub fn check_rcode(rcode: u8, fqdn: &str, server: &str) -> Result<(), Error> {
+    match rcode {
+        RCODE_NXDOMAIN => Err(Error::NxDomain(fqdn.to_string())),
+        RCODE_SERVFAIL => Err(Error::ServerFailure(server.to_string())),
+        RCODE_REFUSED => Err(Error::Refused(server.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Tell timeouts apart from other I/O errors.
+pub fn io_error(err: io::Error) -> Error {
+    match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => Error::Timeout,
+        _ => Error::Io(err),
+    }
+}
+
+pub fn malformed(what: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("Malformed DNS message: {}", what))
+}
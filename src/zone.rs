@@ -0,0 +1,322 @@
+use self::super::{Resolver, Lookup, Error};
+use self::super::resolver::lookup_following;
+use self::super::wire::{self, Message, Record};
+use std::str::FromStr;
+use std::path::Path;
+use std::fs;
+
+
+/// A lookup source answering from a BIND-style master zone file, for when there's no DNS to ask.
+///
+/// `$ORIGIN`, `$TTL`, `@`, relative names, omitted owners, parenthesised multi-line records, comments
+/// and multi-string, escaped TXT records are understood. TXT, CNAME and DNAME records are used, others only make their
+/// names exist. `$INCLUDE` isn't supported.
+///
+/// Lookups report the TTLs and the CNAMEs followed.
+///
+/// # Examples
+///
+/// ```
+/// # use openalias::{ZoneFile, addresses_with};
+/// let zone: ZoneFile = r#"
+/// $ORIGIN example.com.
+/// $TTL 1h
+/// @          IN SOA  ns1 hostmaster 1 7200 3600 1209600 3600
+/// donate        TXT  ( "oa1:xmr recipient_address=46BeWrHpwXmHDpDEUmZBWZfoQpdc6HaERCNmx1pEYL2rAcuwufPN9rXHHtyUA4QVy66qeFQkn6sfK8aHYjA3jk3o1Bv16em;"
+///                      " recipient_name=Example\032Inc.;" )  ; split, as records longer than 255 bytes must be
+///            300 IN  TXT  "oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;"
+/// tips          CNAME donate
+/// "#.parse().unwrap();
+///
+/// let addresses = addresses_with(&zone, "tips@example.com").unwrap();
+/// assert_eq!(addresses.len(), 2);
+/// assert_eq!(addresses[0].cryptocurrency, "xmr");
+/// assert_eq!(addresses[0].recipient_name, Some("Example Inc.".to_string()));
+/// assert_eq!(addresses[1].cryptocurrency, "btc");
+///
+/// assert!(addresses_with(&zone, "nobody@example.com").is_err());
+/// ```
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct ZoneFile {
+    records: Vec<Record>,
+}
+
+impl ZoneFile {
+    /// Read and parse the specified zone file, with relative names relative to the root until an `$ORIGIN` directive.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use openalias::{ZoneFile, addresses_with};
+    /// let zone = ZoneFile::open("example.com.zone").unwrap();
+    /// println!("{:?}", addresses_with(&zone, "donate@example.com"));
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ZoneFile, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Parse a zone, with relative names relative to the specified origin until an `$ORIGIN` directive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use openalias::{ZoneFile, Resolver};
+    /// let zone = ZoneFile::parse("$TTL 3600\ndonate TXT \"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;\"",
+    ///                            "example.com.").unwrap();
+    /// assert_eq!(zone.lookup("donate.example.com.").unwrap().records[0].ttl, Some(3600));
+    ///
+    /// assert!(ZoneFile::parse("donate TXT \"oa1:btc", "example.com.").is_err());
+    /// assert!(ZoneFile::parse("donate TXT \"no TTL anywhere\"", "example.com.").is_err());
+    /// ```
+    pub fn parse(zone: &str, origin: &str) -> Result<ZoneFile, Error> {
+        let mut origin = absolute(origin, ".");
+        let mut default_ttl = None;
+        let mut last_ttl = None;
+        let mut last_owner = None;
+        let mut records = vec![];
+
+        for (line, continued, tokens) in logical_lines(zone)? {
+            let err = |why: String| Error::ZoneParse(line, why);
+
+            match &tokens[0].to_uppercase()[..] {
+                "$ORIGIN" => {
+                    origin = absolute(tokens.get(1).ok_or_else(|| err("$ORIGIN without a name".to_string()))?, &origin);
+                    continue;
+                }
+                "$TTL" => {
+                    default_ttl = Some(parse_ttl(tokens.get(1).ok_or_else(|| err("$TTL without a TTL".to_string()))?).map_err(&err)?);
+                    continue;
+                }
+                directive if directive.starts_with('$') => return Err(err(format!("unsupported directive {}", directive))),
+                _ => {}
+            }
+
+            let mut tokens = &tokens[..];
+            let owner = if continued {
+                last_owner.clone().ok_or_else(|| err("no previous owner name".to_string()))?
+            } else {
+                let owner = absolute(&tokens[0], &origin);
+                tokens = &tokens[1..];
+                owner
+            };
+            last_owner = Some(owner.clone());
+
+            let mut ttl = None;
+            let mut class = None;
+            loop {
+                match tokens.first() {
+                    Some(t) if ttl.is_none() && t.starts_with(|c: char| c.is_ascii_digit()) => ttl = Some(parse_ttl(t).map_err(&err)?),
+                    Some(t) if class.is_none() && ["IN", "CH", "HS", "CS"].contains(&&t.to_uppercase()[..]) => class = Some(t.to_uppercase()),
+                    _ => break,
+                }
+                tokens = &tokens[1..];
+            }
+            if ttl.is_some() {
+                last_ttl = ttl;
+            }
+            let ttl = ttl.or(default_ttl).or(last_ttl).ok_or_else(|| err("no TTL, and no $TTL before".to_string()))?;
+            if class.as_ref().is_some_and(|c| c != "IN") {
+                continue;
+            }
+
+            let rtype = tokens.first().ok_or_else(|| err("no record type".to_string()))?.to_uppercase();
+            let rdata = &tokens[1..];
+            let (rtype, data) = match &rtype[..] {
+                "TXT" => (wire::TYPE_TXT, txt_rdata(rdata).map_err(&err)?),
+                "CNAME" | "DNAME" => {
+                    let target = rdata.first().ok_or_else(|| err(format!("{} without a target", rtype)))?;
+                    (if rtype == "CNAME" { wire::TYPE_CNAME } else { wire::TYPE_DNAME }, wire::name_to_wire(&absolute(target, &origin)).map_err(|e| err(e.to_string()))?)
+                }
+                _ => (0, vec![]),
+            };
+
+            records.push(Record {
+                name: owner,
+                rtype,
+                class: wire::CLASS_IN,
+                ttl,
+                data,
+            });
+        }
+
+        Ok(ZoneFile { records })
+    }
+}
+
+impl FromStr for ZoneFile {
+    type Err = Error;
+
+    /// Parse a zone, with relative names relative to the root until an `$ORIGIN` directive.
+    fn from_str(s: &str) -> Result<ZoneFile, Error> {
+        ZoneFile::parse(s, ".")
+    }
+}
+
+impl Resolver for ZoneFile {
+    fn txt_records(&self, fqdn: &str) -> Result<Vec<Vec<u8>>, Error> {
+        Ok(self.lookup(fqdn)?.records.into_iter().map(|r| r.data).collect())
+    }
+
+    fn lookup(&self, fqdn: &str) -> Result<Lookup, Error> {
+        let lookup = lookup_following(fqdn, |name| {
+            let redirects = |r: &Record| r.rtype == wire::TYPE_DNAME && r.name != name && wire::is_subdomain(name, &r.name);
+            if !self.records.iter().any(|r| wire::is_subdomain(&r.name, name) || redirects(r)) {
+                return Err(Error::NxDomain(name.to_string()));
+            }
+
+            Ok(Message {
+                id: 0,
+                flags: 0,
+                questions: vec![],
+                answers: self.records.iter().filter(|r| r.name == name || redirects(r)).cloned().collect(),
+                authority: vec![],
+                additional: vec![],
+            })
+        })?;
+
+        if lookup.records.is_empty() {
+            return Err(Error::NoTxtData(fqdn.to_string()));
+        }
+        Ok(lookup)
+    }
+}
+
+
+/// Split the zone into records, returning each one's line number, whether it continues the previous owner (i.e. starts
+/// with a blank), and its tokens, with quoted strings still quoted.
+fn logical_lines(zone: &str) -> Result<Vec<(usize, bool, Vec<String>)>, Error> {
+    let mut lines = vec![];
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut line = 1;
+    let mut start_line = 1;
+    let mut continued = false;
+    let mut at_line_start = true;
+    let mut quoted = false;
+    let mut depth = 0usize;
+
+    let mut chars = zone.chars().peekable();
+    while let Some(c) = chars.next() {
+        if at_line_start && depth == 0 {
+            continued = c == ' ' || c == '\t';
+            start_line = line;
+            at_line_start = false;
+        }
+
+        match c {
+            '\n' if quoted => return Err(Error::ZoneParse(line, "unterminated quoted string".to_string())),
+            '\\' => {
+                token.push(c);
+                match chars.next() {
+                    Some('\n') | None => return Err(Error::ZoneParse(line, "escape at end of line".to_string())),
+                    Some(e) => token.push(e),
+                }
+            }
+            '"' => {
+                token.push(c);
+                quoted = !quoted;
+            }
+            _ if quoted => token.push(c),
+            ';' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' | ')' | ' ' | '\t' | '\r' | '\n' => {
+                if !token.is_empty() {
+                    tokens.push(token);
+                    token = String::new();
+                }
+
+                match c {
+                    '(' => depth += 1,
+                    ')' if depth == 0 => return Err(Error::ZoneParse(line, "unbalanced parenthesis".to_string())),
+                    ')' => depth -= 1,
+                    '\n' => {
+                        if depth == 0 {
+                            if !tokens.is_empty() {
+                                lines.push((start_line, continued, tokens));
+                                tokens = vec![];
+                            }
+                            at_line_start = true;
+                        }
+                        line += 1;
+                    }
+                    _ => {}
+                }
+            }
+            _ => token.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(Error::ZoneParse(line, "unterminated quoted string".to_string()));
+    }
+    if depth != 0 {
+        return Err(Error::ZoneParse(start_line, "unbalanced parenthesis".to_string()));
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    if !tokens.is_empty() {
+        lines.push((start_line, continued, tokens));
+    }
+    Ok(lines)
+}
+
+/// Make the name absolute and lowercase, `@` being the origin.
+fn absolute(name: &str, origin: &str) -> String {
+    let name = name.to_lowercase();
+    if name == "@" {
+        origin.to_string()
+    } else if name.ends_with('.') && !name.ends_with("\\.") {
+        name
+    } else if origin == "." {
+        name + "."
+    } else {
+        format!("{}.{}", name, origin)
+    }
+}
+
+/// Parse "3600", or "1h30m"-style BIND TTLs.
+fn parse_ttl(ttl: &str) -> Result<u32, String> {
+    let mut total = 0u64;
+    let mut value = None;
+    for c in ttl.chars() {
+        match c.to_ascii_lowercase() {
+            d @ '0'..='9' => value = Some(value.unwrap_or(0u64) * 10 + d.to_digit(10).unwrap() as u64),
+            unit @ ('s' | 'm' | 'h' | 'd' | 'w') => {
+                let multiplier = match unit {
+                    's' => 1,
+                    'm' => 60,
+                    'h' => 60 * 60,
+                    'd' => 24 * 60 * 60,
+                    _ => 7 * 24 * 60 * 60,
+                };
+                total += value.take().ok_or_else(|| format!("{} is not a valid TTL", ttl))? * multiplier;
+            }
+            _ => return Err(format!("{} is not a valid TTL", ttl)),
+        }
+        if total + value.unwrap_or(0) > u32::MAX as u64 {
+            return Err(format!("{} is not a valid TTL", ttl));
+        }
+    }
+    Ok((total + value.unwrap_or(0)) as u32)
+}
+
+/// Encode the (quoted or not) character-strings as TXT RDATA.
+fn txt_rdata(rdata: &[String]) -> Result<Vec<u8>, String> {
+    if rdata.is_empty() {
+        return Err("TXT without text".to_string());
+    }
+
+    let mut out = vec![];
+    for segment in wire::character_strings(rdata.join(" ").as_bytes())? {
+        if segment.len() > 255 {
+            return Err("TXT character-string longer than 255 bytes".to_string());
+        }
+        out.push(segment.len() as u8);
+        out.extend(segment);
+    }
+    Ok(out)
+}
@@ -0,0 +1,83 @@
+//! Non-blocking lookups against a local name server, inside a tokio runtime.
+
+
+#![cfg(feature = "async")]
+
+extern crate openalias;
+extern crate tokio;
+
+use openalias::{ResolverConfig, address_strings_with_config_async};
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::io::{Read, Write};
+use std::time::Duration;
+use std::thread;
+
+
+const RECORD: &[u8] = b"oa1:btc recipient_address=1CgLs6CxXMAY4Pj4edQq5vyaFoP9NdqVKH;";
+
+
+#[test]
+fn udp() {
+    let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = sock.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        let (len, from) = sock.recv_from(&mut buf).unwrap();
+        sock.send_to(&answer(&buf[..len], false), from).unwrap();
+    });
+
+    assert_eq!(look_up(server), vec![String::from_utf8(RECORD.to_vec()).unwrap()]);
+}
+
+#[test]
+fn truncated_retried_over_tcp() {
+    let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = tcp.local_addr().unwrap();
+    let udp = UdpSocket::bind(server).unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        let (len, from) = udp.recv_from(&mut buf).unwrap();
+        udp.send_to(&answer(&buf[..len], true), from).unwrap();
+
+        let (mut conn, _) = tcp.accept().unwrap();
+        let mut len = [0; 2];
+        conn.read_exact(&mut len).unwrap();
+        let mut query = vec![0; u16::from_be_bytes(len) as usize];
+        conn.read_exact(&mut query).unwrap();
+        let response = answer(&query, false);
+        conn.write_all(&(response.len() as u16).to_be_bytes()).unwrap();
+        conn.write_all(&response).unwrap();
+    });
+
+    assert_eq!(look_up(server), vec![String::from_utf8(RECORD.to_vec()).unwrap()]);
+}
+
+
+fn look_up(server: SocketAddr) -> Vec<String> {
+    let config = ResolverConfig::new().name_server(server).timeout(Duration::from_secs(2)).attempts(1);
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(address_strings_with_config_async(&config, "donate@example.com")).unwrap()
+}
+
+/// Echo the query's question, without any OPT record, with `RECORD` as the answer, or none if truncated.
+fn answer(query: &[u8], truncated: bool) -> Vec<u8> {
+    let mut question_end = 12;
+    while query[question_end] != 0 {
+        question_end += 1 + query[question_end] as usize;
+    }
+    question_end += 5;
+
+    let mut response = query[..2].to_vec();
+    if truncated {
+        response.extend_from_slice(&[0x83, 0x80, 0, 1, 0, 0, 0, 0, 0, 0]);
+        response.extend_from_slice(&query[12..question_end]);
+    } else {
+        response.extend_from_slice(&[0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]);
+        response.extend_from_slice(&query[12..question_end]);
+        response.extend_from_slice(&[0xC0, 12, 0, 16, 0, 1, 0, 0, 0, 60]);
+        response.extend_from_slice(&(RECORD.len() as u16 + 1).to_be_bytes());
+        response.push(RECORD.len() as u8);
+        response.extend_from_slice(RECORD);
+    }
+    response
+}
@@ -0,0 +1,424 @@
+//! DNSSEC validation of a locally-signed zone, served from a local name server.
+//!
+//! The example. zone is signed anew each time with an ECDSA P-256 (algorithm 13) key, trusted via a trust anchor.
+
+
+extern crate openalias;
+extern crate ring;
+
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use openalias::{DnssecStatus, ResolverConfig, TrustAnchor, Validator, Resolver};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::net::{SocketAddr, UdpSocket};
+use std::collections::HashMap;
+use ring::rand::SystemRandom;
+use ring::digest;
+use std::thread;
+
+
+const TYPE_NS: u16 = 2;
+const TYPE_CNAME: u16 = 5;
+const TYPE_TXT: u16 = 16;
+const TYPE_DNAME: u16 = 39;
+const TYPE_DS: u16 = 43;
+const TYPE_RRSIG: u16 = 46;
+const TYPE_NSEC: u16 = 47;
+const TYPE_DNSKEY: u16 = 48;
+const TYPE_NSEC3: u16 = 50;
+
+const RECORD: &str = "oa1:btc recipient_address=1CgLs6CxXMAY4Pj4edQq5vyaFoP9NdqVKH;";
+
+
+#[test]
+fn secure() {
+    let zone = Zone::new();
+    let status = zone.status_of("donate.example");
+    assert_eq!(status, DnssecStatus::Secure);
+}
+
+#[test]
+fn tampered_record_bogus() {
+    let mut zone = Zone::new();
+    let tampered = txt_rdata("oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;");
+    zone.responses.get_mut(&("donate.example.".to_string(), TYPE_TXT)).unwrap().0[0].data = tampered;
+    let status = zone.status_of("donate.example");
+    assert_eq!(status, DnssecStatus::Bogus("signature mismatch".to_string()));
+}
+
+#[test]
+fn stripped_signature_bogus() {
+    let mut zone = Zone::new();
+    zone.responses.get_mut(&("donate.example.".to_string(), TYPE_TXT)).unwrap().0.retain(|r| r.rtype != TYPE_RRSIG);
+    let status = zone.status_of("donate.example");
+    assert_eq!(status, DnssecStatus::Bogus("no signatures by example. over type 16 records for donate.example.".to_string()));
+}
+
+#[test]
+fn expired_signature_bogus() {
+    let mut zone = Zone::new();
+    let txt = Rr::new("donate.example.", TYPE_TXT, txt_rdata(RECORD));
+    let sig = zone.sign_at(&[&txt], "donate.example.", now() - 7200, now() - 3600);
+    zone.respond("donate.example.", TYPE_TXT, vec![txt, sig], vec![]);
+    let status = zone.status_of("donate.example");
+    assert_eq!(status, DnssecStatus::Bogus("signature by example. expired".to_string()));
+}
+
+#[test]
+fn proven_unsigned_delegation_insecure() {
+    let mut zone = Zone::new();
+    let nsec = Rr::new("insecure.example.", TYPE_NSEC, nsec_rdata("wild.example.", &[TYPE_NS, TYPE_RRSIG, TYPE_NSEC]));
+    let sig = zone.sign(&[&nsec], "insecure.example.");
+    zone.respond("insecure.example.", TYPE_DS, vec![], vec![nsec, sig]);
+    zone.respond("donate.insecure.example.", TYPE_TXT, vec![Rr::new("donate.insecure.example.", TYPE_TXT, txt_rdata(RECORD))], vec![]);
+    let status = zone.status_of("donate.insecure.example");
+    assert_eq!(status, DnssecStatus::Insecure);
+}
+
+#[test]
+fn unproven_unsigned_delegation_bogus() {
+    let mut zone = Zone::new();
+    zone.respond("insecure.example.", TYPE_DS, vec![], vec![]);
+    zone.respond("donate.insecure.example.", TYPE_TXT, vec![Rr::new("donate.insecure.example.", TYPE_TXT, txt_rdata(RECORD))], vec![]);
+    let status = zone.status_of("donate.insecure.example");
+    assert_eq!(status, DnssecStatus::Bogus("no proof insecure.example. has no DS records".to_string()));
+}
+
+#[test]
+fn nsec3_proven_unsigned_delegation_insecure() {
+    let mut zone = Zone::new();
+    let nsec3 = Rr::new(&format!("{}.example.", nsec3_hash("insecure.example.", b"\xAB")),
+                        TYPE_NSEC3,
+                        nsec3_rdata(b"\xAB", &nsec3_hash("wild.example.", b"\xAB"), &[TYPE_NS]));
+    let sig = zone.sign(&[&nsec3], &nsec3.name.clone());
+    zone.respond("insecure.example.", TYPE_DS, vec![], vec![nsec3, sig]);
+    zone.respond("donate.insecure.example.", TYPE_TXT, vec![Rr::new("donate.insecure.example.", TYPE_TXT, txt_rdata(RECORD))], vec![]);
+    let status = zone.status_of("donate.insecure.example");
+    assert_eq!(status, DnssecStatus::Insecure);
+}
+
+#[test]
+fn wildcard_with_proof_secure() {
+    let mut zone = Zone::wildcard();
+    let nsec = Rr::new("*.wild.example.", TYPE_NSEC, nsec_rdata("z.wild.example.", &[TYPE_TXT, TYPE_RRSIG, TYPE_NSEC]));
+    let sig = zone.sign(&[&nsec], "*.wild.example.");
+    zone.responses.get_mut(&("donate.wild.example.".to_string(), TYPE_TXT)).unwrap().1 = vec![nsec, sig];
+    let status = zone.status_of("donate.wild.example");
+    assert_eq!(status, DnssecStatus::Secure);
+}
+
+#[test]
+fn wildcard_without_proof_bogus() {
+    let zone = Zone::wildcard();
+    let status = zone.status_of("donate.wild.example");
+    assert_eq!(status,
+               DnssecStatus::Bogus("donate.wild.example. expanded from a wildcard, without proof donate.wild.example. doesn't exist".to_string()));
+}
+
+#[test]
+fn signed_cname_secure() {
+    let mut zone = Zone::new();
+    zone.not_delegation("alias.example.", &[TYPE_CNAME, TYPE_RRSIG, TYPE_NSEC]);
+    let cname = Rr::new("alias.example.", TYPE_CNAME, name_to_wire("donate.example."));
+    let cname_sig = zone.sign(&[&cname], "alias.example.");
+    let mut answers = vec![cname, cname_sig];
+    answers.extend(zone.responses[&("donate.example.".to_string(), TYPE_TXT)].0.clone());
+    zone.respond("alias.example.", TYPE_TXT, answers, vec![]);
+
+    assert_eq!(zone.status_of("alias.example"), DnssecStatus::Secure);
+    let lookup = zone.validator().lookup("alias.example.").unwrap();
+    assert_eq!(lookup.cname_chain, vec!["donate.example.".to_string()]);
+}
+
+#[test]
+fn unsigned_cname_bogus() {
+    let mut zone = Zone::new();
+    zone.not_delegation("alias.example.", &[TYPE_CNAME, TYPE_RRSIG, TYPE_NSEC]);
+    let mut answers = vec![Rr::new("alias.example.", TYPE_CNAME, name_to_wire("donate.example."))];
+    answers.extend(zone.responses[&("donate.example.".to_string(), TYPE_TXT)].0.clone());
+    zone.respond("alias.example.", TYPE_TXT, answers, vec![]);
+
+    let status = zone.status_of("alias.example");
+    assert_eq!(status, DnssecStatus::Bogus("no signatures by example. over type 5 records for alias.example.".to_string()));
+}
+
+#[test]
+fn signed_dname_secure() {
+    let mut zone = Zone::new();
+    zone.not_delegation("moved.example.", &[TYPE_DNAME, TYPE_RRSIG, TYPE_NSEC]);
+    let dname = Rr::new("moved.example.", TYPE_DNAME, name_to_wire("example."));
+    let dname_sig = zone.sign(&[&dname], "moved.example.");
+    let mut answers = vec![dname, dname_sig, Rr::new("donate.moved.example.", TYPE_CNAME, name_to_wire("donate.example."))];
+    answers.extend(zone.responses[&("donate.example.".to_string(), TYPE_TXT)].0.clone());
+    zone.respond("donate.moved.example.", TYPE_TXT, answers, vec![]);
+
+    assert_eq!(zone.status_of("donate.moved.example"), DnssecStatus::Secure);
+}
+
+
+/// Answer and authority sections to respond to each query, by name and type, with.
+type Responses = HashMap<(String, u16), (Vec<Rr>, Vec<Rr>)>;
+
+#[derive(Debug, Clone)]
+struct Rr {
+    name: String,
+    rtype: u16,
+    data: Vec<u8>,
+}
+
+impl Rr {
+    fn new(name: &str, rtype: u16, data: Vec<u8>) -> Rr {
+        Rr {
+            name: name.to_string(),
+            rtype,
+            data,
+        }
+    }
+}
+
+/// The example. zone's key, and the responses to queries about it.
+struct Zone {
+    key: EcdsaKeyPair,
+    dnskey: Vec<u8>,
+    responses: Responses,
+}
+
+impl Zone {
+    /// A zone with a signed donate.example. TXT record, and NSEC proof it's not a delegation.
+    fn new() -> Zone {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let mut dnskey = vec![0x01, 0x01, 3, 13];
+        dnskey.extend_from_slice(&key.public_key().as_ref()[1..]);
+
+        let mut zone = Zone {
+            key,
+            dnskey,
+            responses: HashMap::new(),
+        };
+
+        let dnskey = Rr::new("example.", TYPE_DNSKEY, zone.dnskey.clone());
+        let sig = zone.sign(&[&dnskey], "example.");
+        zone.respond("example.", TYPE_DNSKEY, vec![dnskey, sig], vec![]);
+
+        let nsec = Rr::new("donate.example.", TYPE_NSEC, nsec_rdata("insecure.example.", &[TYPE_TXT, TYPE_RRSIG, TYPE_NSEC]));
+        let sig = zone.sign(&[&nsec], "donate.example.");
+        zone.respond("donate.example.", TYPE_DS, vec![], vec![nsec, sig]);
+
+        let txt = Rr::new("donate.example.", TYPE_TXT, txt_rdata(RECORD));
+        let sig = zone.sign(&[&txt], "donate.example.");
+        zone.respond("donate.example.", TYPE_TXT, vec![txt, sig], vec![]);
+
+        zone
+    }
+
+    /// A zone with donate.wild.example. expanded from a signed *.wild.example. TXT record, but no proof it should've been.
+    fn wildcard() -> Zone {
+        let mut zone = Zone::new();
+
+        let nsec = Rr::new("wild.example.", TYPE_NSEC, nsec_rdata("*.wild.example.", &[TYPE_TXT, TYPE_RRSIG, TYPE_NSEC]));
+        let sig = zone.sign(&[&nsec], "wild.example.");
+        zone.respond("wild.example.", TYPE_DS, vec![], vec![nsec, sig]);
+
+        let nsec = Rr::new("*.wild.example.", TYPE_NSEC, nsec_rdata("z.wild.example.", &[TYPE_TXT, TYPE_RRSIG, TYPE_NSEC]));
+        let sig = zone.sign(&[&nsec], "*.wild.example.");
+        zone.respond("donate.wild.example.", TYPE_DS, vec![], vec![nsec, sig]);
+
+        let txt = Rr::new("donate.wild.example.", TYPE_TXT, txt_rdata(RECORD));
+        let sig = zone.sign(&[&txt], "*.wild.example.");
+        zone.respond("donate.wild.example.", TYPE_TXT, vec![txt, sig], vec![]);
+
+        zone
+    }
+
+    fn respond(&mut self, name: &str, rtype: u16, answers: Vec<Rr>, authority: Vec<Rr>) {
+        self.responses.insert((name.to_string(), rtype), (answers, authority));
+    }
+
+    /// Sign the RRset, valid for the hour around now, as if its owner were `signed_owner`, e.g. a wildcard.
+    fn sign(&self, rrset: &[&Rr], signed_owner: &str) -> Rr {
+        self.sign_at(rrset, signed_owner, now() - 3600, now() + 3600)
+    }
+
+    fn sign_at(&self, rrset: &[&Rr], signed_owner: &str, inception: u32, expiration: u32) -> Rr {
+        let labels = signed_owner.split('.').filter(|l| !l.is_empty() && *l != "*").count();
+
+        let mut rdata = rrset[0].rtype.to_be_bytes().to_vec();
+        rdata.extend_from_slice(&[13, labels as u8]);
+        rdata.extend_from_slice(&3600u32.to_be_bytes());
+        rdata.extend_from_slice(&expiration.to_be_bytes());
+        rdata.extend_from_slice(&inception.to_be_bytes());
+        rdata.extend_from_slice(&key_tag(&self.dnskey).to_be_bytes());
+        rdata.extend(name_to_wire("example."));
+
+        let mut rdatas: Vec<_> = rrset.iter().map(|r| &r.data).collect();
+        rdatas.sort();
+        let mut signed = rdata.clone();
+        for data in rdatas {
+            signed.extend(name_to_wire(signed_owner));
+            signed.extend_from_slice(&rrset[0].rtype.to_be_bytes());
+            signed.extend_from_slice(&[0, 1]);
+            signed.extend_from_slice(&3600u32.to_be_bytes());
+            signed.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            signed.extend(data);
+        }
+
+        rdata.extend_from_slice(self.key.sign(&SystemRandom::new(), &signed).unwrap().as_ref());
+        Rr::new(&rrset[0].name, TYPE_RRSIG, rdata)
+    }
+
+    /// Presentation-form DS RDATA for the key.
+    fn ds(&self) -> String {
+        let mut data = name_to_wire("example.");
+        data.extend(&self.dnskey);
+        let digest: String = digest::digest(&digest::SHA256, &data).as_ref().iter().map(|b| format!("{:02X}", b)).collect();
+        format!("{} 13 2 {}", key_tag(&self.dnskey), digest)
+    }
+
+    /// Serve the zone, and validate the TXT records of the specified alias with it as the trust anchor.
+    fn status_of(&self, alias: &str) -> DnssecStatus {
+        let records = self.validator().address_strings(alias).unwrap();
+        assert_eq!(records.len(), 1);
+        records[0].status.clone()
+    }
+
+    fn validator(&self) -> Validator {
+        let mut validator = Validator::with_config(&ResolverConfig::new().name_server(self.serve())).unwrap();
+        validator.trust_anchors = vec![TrustAnchor::new("example.", &[self.ds()]).unwrap()];
+        validator
+    }
+
+    /// Respond to the DS query for the name with signed NSEC proof it's a name in the zone, with the specified types.
+    fn not_delegation(&mut self, name: &str, types: &[u16]) {
+        let nsec = Rr::new(name, TYPE_NSEC, nsec_rdata("z.example.", types));
+        let sig = self.sign(&[&nsec], name);
+        self.respond(name, TYPE_DS, vec![], vec![nsec, sig]);
+    }
+
+    fn serve(&self) -> SocketAddr {
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = sock.local_addr().unwrap();
+        let responses = self.responses.clone();
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            loop {
+                let (len, from) = sock.recv_from(&mut buf).unwrap();
+                sock.send_to(&answer(&buf[..len], &responses), from).unwrap();
+            }
+        });
+        server
+    }
+}
+
+fn answer(query: &[u8], responses: &Responses) -> Vec<u8> {
+    let mut name = String::new();
+    let mut pos = 12;
+    while query[pos] != 0 {
+        let len = query[pos] as usize;
+        name.push_str(&String::from_utf8_lossy(&query[pos + 1..pos + 1 + len]).to_lowercase());
+        name.push('.');
+        pos += 1 + len;
+    }
+    let rtype = u16::from_be_bytes([query[pos + 1], query[pos + 2]]);
+    let question_end = pos + 5;
+
+    let empty = (vec![], vec![]);
+    let (answers, authority) = responses.get(&(name, rtype)).unwrap_or(&empty);
+    let mut response = query[..2].to_vec();
+    response.extend_from_slice(&[0x81, 0x80, 0, 1]);
+    response.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+    response.extend_from_slice(&(authority.len() as u16).to_be_bytes());
+    response.extend_from_slice(&[0, 0]);
+    response.extend_from_slice(&query[12..question_end]);
+    for rr in answers.iter().chain(authority) {
+        response.extend(name_to_wire(&rr.name));
+        response.extend_from_slice(&rr.rtype.to_be_bytes());
+        response.extend_from_slice(&[0, 1]);
+        response.extend_from_slice(&3600u32.to_be_bytes());
+        response.extend_from_slice(&(rr.data.len() as u16).to_be_bytes());
+        response.extend(&rr.data);
+    }
+    response
+}
+
+
+fn now() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as u32
+}
+
+fn name_to_wire(name: &str) -> Vec<u8> {
+    let mut out = vec![];
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        out.push(label.len() as u8);
+        out.extend(label.to_lowercase().bytes());
+    }
+    out.push(0);
+    out
+}
+
+fn txt_rdata(text: &str) -> Vec<u8> {
+    let mut data = vec![text.len() as u8];
+    data.extend(text.bytes());
+    data
+}
+
+/// Type bit map window 0, as per RFC 4034 section 4.1.2.
+fn type_bitmap(types: &[u16]) -> Vec<u8> {
+    let mut bitmap = vec![0; *types.iter().max().unwrap() as usize / 8 + 1];
+    for &rtype in types {
+        bitmap[rtype as usize / 8] |= 0x80 >> (rtype % 8);
+    }
+    let mut out = vec![0, bitmap.len() as u8];
+    out.extend(bitmap);
+    out
+}
+
+fn nsec_rdata(next: &str, types: &[u16]) -> Vec<u8> {
+    let mut data = name_to_wire(next);
+    data.extend(type_bitmap(types));
+    data
+}
+
+/// NSEC3 RDATA with SHA-1, no iterations, and the opt-out flag clear.
+fn nsec3_rdata(salt: &[u8], next_hash: &str, types: &[u16]) -> Vec<u8> {
+    let next = base32hex_decode(next_hash);
+    let mut data = vec![1, 0, 0, 0, salt.len() as u8];
+    data.extend(salt);
+    data.push(next.len() as u8);
+    data.extend(next);
+    data.extend(type_bitmap(types));
+    data
+}
+
+fn nsec3_hash(name: &str, salt: &[u8]) -> String {
+    let mut data = name_to_wire(name);
+    data.extend(salt);
+    digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &data)
+        .as_ref()
+        .chunks(5)
+        .flat_map(|chunk| {
+            let bits = chunk.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+            (0..8).rev().map(move |i| b"0123456789abcdefghijklmnopqrstuv"[(bits >> (i * 5)) as usize & 0x1F] as char)
+        })
+        .collect()
+}
+
+fn base32hex_decode(text: &str) -> Vec<u8> {
+    let bits: Vec<u8> = text.bytes().map(|c| if c.is_ascii_digit() { c - b'0' } else { c - b'a' + 10 }).collect();
+    bits.chunks(8)
+        .flat_map(|chunk| {
+            let value = chunk.iter().fold(0u64, |acc, &b| (acc << 5) | b as u64);
+            (0..5).rev().map(move |i| (value >> (i * 8)) as u8)
+        })
+        .collect()
+}
+
+/// Key tag calculation, as per RFC 4034 appendix B.
+fn key_tag(rdata: &[u8]) -> u16 {
+    let mut ac: u32 = 0;
+    for (i, &b) in rdata.iter().enumerate() {
+        ac += if i & 1 == 0 { (b as u32) << 8 } else { b as u32 };
+    }
+    ac += (ac >> 16) & 0xFFFF;
+    ac as u16
+}
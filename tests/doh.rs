@@ -0,0 +1,112 @@
+//! DNS-over-HTTPS lookups against a local plain-HTTP server, with answers that mustn't be taken.
+
+
+extern crate openalias;
+
+use openalias::{DohFormat, DohResolver, Error, Resolver};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+
+const RECORD: &[u8] = b"oa1:btc recipient_address=1CgLs6CxXMAY4Pj4edQq5vyaFoP9NdqVKH;";
+
+
+#[test]
+fn wire_truncated() {
+    let url = serve(|query| {
+        let mut response = answer(&query);
+        response[2] |= 0x02;
+        response
+    });
+    match DohResolver::new(&url).unwrap().lookup("donate.example.com.") {
+        Err(Error::Truncated(name)) => assert_eq!(name, "donate.example.com."),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn wire_other_question_rejected() {
+    let url = serve(|query| {
+        let mut response = answer(&query);
+        response[12 + 1] = b'x';
+        response
+    });
+    let err = DohResolver::new(&url).unwrap().lookup("donate.example.com.").unwrap_err();
+    assert!(err.to_string().contains("response doesn't match query"), "{}", err);
+}
+
+#[test]
+fn json_truncated() {
+    let url = serve(|_| json(r#"{"Status": 0, "TC": true, "Question": [{"name": "donate.example.com.", "type": 16}]}"#));
+    match DohResolver::new(&url).unwrap().format(DohFormat::Json).lookup("donate.example.com.") {
+        Err(Error::Truncated(name)) => assert_eq!(name, "donate.example.com."),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn json_other_question_rejected() {
+    let url = serve(|_| {
+        json(r#"{"Status": 0, "TC": false, "Question": [{"name": "dxnate.example.com.", "type": 16}], "Answer": [
+                    {"name": "dxnate.example.com.", "type": 16, "TTL": 60, "data": "\"oa1:btc recipient_address=1CgLs6CxXMAY4Pj4edQq5vyaFoP9NdqVKH;\""}]}"#)
+    });
+    let err = DohResolver::new(&url).unwrap().format(DohFormat::Json).lookup("donate.example.com.").unwrap_err();
+    assert!(err.to_string().contains("response doesn't match query"), "{}", err);
+}
+
+#[test]
+fn json_without_question_rejected() {
+    let url = serve(|_| {
+        json(r#"{"Status": 0, "TC": false, "Answer": [
+                    {"name": "donate.example.com.", "type": 16, "TTL": 60, "data": "\"oa1:btc recipient_address=1CgLs6CxXMAY4Pj4edQq5vyaFoP9NdqVKH;\""}]}"#)
+    });
+    let err = DohResolver::new(&url).unwrap().format(DohFormat::Json).lookup("donate.example.com.").unwrap_err();
+    assert!(err.to_string().contains("response doesn't match query"), "{}", err);
+}
+
+
+/// Answer one request with what `respond` makes of its body, returning the URL to ask.
+fn serve<F: FnOnce(Vec<u8>) -> Vec<u8> + Send + 'static>(respond: F) -> String {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/dns-query", server.local_addr().unwrap());
+    thread::spawn(move || {
+        let (conn, _) = server.accept().unwrap();
+        let mut conn = BufReader::new(conn);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            conn.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if line.to_lowercase().starts_with("content-length:") {
+                content_length = line[15..].trim().parse().unwrap();
+            }
+        }
+        let mut query = vec![0; content_length];
+        conn.read_exact(&mut query).unwrap();
+
+        let response = respond(query);
+        let mut conn = conn.into_inner();
+        write!(conn, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", response.len()).unwrap();
+        conn.write_all(&response).unwrap();
+    });
+    url
+}
+
+fn json(body: &str) -> Vec<u8> {
+    body.as_bytes().to_vec()
+}
+
+/// Echo the query's question with `RECORD` as the answer.
+fn answer(query: &[u8]) -> Vec<u8> {
+    let mut response = query.to_vec();
+    response[2] |= 0x80;
+    response[7] = 1;
+    response.extend_from_slice(&[0xC0, 12, 0, 16, 0, 1, 0, 0, 0, 60]);
+    response.extend_from_slice(&(RECORD.len() as u16 + 1).to_be_bytes());
+    response.push(RECORD.len() as u8);
+    response.extend_from_slice(RECORD);
+    response
+}
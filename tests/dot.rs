@@ -0,0 +1,111 @@
+//! SPKI-pinned DNS-over-TLS lookups against a local server.
+//!
+//! The fixtures are two self-signed P-256 certificates for dot.example.com and 127.0.0.1, made with
+//!
+//! ```sh
+//! openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out key.pem
+//! openssl pkcs8 -topk8 -nocrypt -in key.pem -outform der -out server.key.der
+//! openssl req -x509 -new -key key.pem -subj "/CN=dot.example.com" \
+//!     -addext "subjectAltName=DNS:dot.example.com,IP:127.0.0.1" -days 36500 -outform der -out server.der
+//! ```
+//!
+//! and likewise for impostor.der and impostor.key.der.
+
+
+extern crate openalias;
+extern crate rustls;
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use openalias::{DotResolver, Resolver, SpkiPin};
+use std::net::{SocketAddr, TcpListener};
+use std::io::{Read, Write};
+use std::time::Duration;
+use std::sync::Arc;
+use std::thread;
+
+
+static SERVER_CERT: &[u8] = include_bytes!("fixtures/dot/server.der");
+static SERVER_KEY: &[u8] = include_bytes!("fixtures/dot/server.key.der");
+static IMPOSTOR_CERT: &[u8] = include_bytes!("fixtures/dot/impostor.der");
+static IMPOSTOR_KEY: &[u8] = include_bytes!("fixtures/dot/impostor.key.der");
+
+/// SHA-256 of server.der's SubjectPublicKeyInfo.
+const SERVER_PIN: &str = "XOmdsbWHYHMFeqFbmcJ2nTzAg8ttVJqt09vL0nmpdK0=";
+
+const RECORD: &[u8] = b"oa1:btc recipient_address=1CgLs6CxXMAY4Pj4edQq5vyaFoP9NdqVKH;";
+
+
+#[test]
+fn pinned_server_trusted() {
+    let server = serve(&[SERVER_CERT], SERVER_KEY);
+    let lookup = resolver(server).lookup("donate.example.com.").unwrap();
+    assert_eq!(lookup.records[0].data, RECORD);
+}
+
+#[test]
+fn pinned_certificate_as_intermediate_rejected() {
+    let server = serve(&[IMPOSTOR_CERT, SERVER_CERT], IMPOSTOR_KEY);
+    let err = resolver(server).lookup("donate.example.com.").unwrap_err();
+    assert!(err.to_string().contains("pinned"), "{}", err);
+}
+
+#[test]
+fn unpinned_server_rejected() {
+    let server = serve(&[IMPOSTOR_CERT], IMPOSTOR_KEY);
+    let err = resolver(server).lookup("donate.example.com.").unwrap_err();
+    assert!(err.to_string().contains("pinned"), "{}", err);
+}
+
+
+fn resolver(server: SocketAddr) -> DotResolver {
+    DotResolver::new(server).pin(SERVER_PIN.parse::<SpkiPin>().unwrap()).timeout(Duration::from_secs(5))
+}
+
+/// Answer one DNS-over-TLS query with a TXT record, presenting the specified certificate chain.
+fn serve(chain: &[&'static [u8]], key: &'static [u8]) -> SocketAddr {
+    let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(chain.iter().map(|&cert| CertificateDer::from(cert)).collect(),
+                          PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key)))
+        .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (sock, _) = listener.accept().unwrap();
+        let mut conn = StreamOwned::new(ServerConnection::new(Arc::new(config)).unwrap(), sock);
+
+        let mut len = [0; 2];
+        if conn.read_exact(&mut len).is_err() {
+            return;
+        }
+        let mut query = vec![0; u16::from_be_bytes(len) as usize];
+        conn.read_exact(&mut query).unwrap();
+        let response = answer(&query);
+        conn.write_all(&(response.len() as u16).to_be_bytes()).unwrap();
+        conn.write_all(&response).unwrap();
+        conn.flush().unwrap();
+    });
+    server
+}
+
+/// Echo the query's question with `RECORD` as the answer.
+fn answer(query: &[u8]) -> Vec<u8> {
+    let mut question_end = 12;
+    while query[question_end] != 0 {
+        question_end += 1 + query[question_end] as usize;
+    }
+    question_end += 5;
+
+    let mut response = query[..2].to_vec();
+    response.extend_from_slice(&[0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]);
+    response.extend_from_slice(&query[12..question_end]);
+    response.extend_from_slice(&[0xC0, 12, 0, 16, 0, 1, 0, 0, 0, 60]);
+    response.extend_from_slice(&(RECORD.len() as u16 + 1).to_be_bytes());
+    response.push(RECORD.len() as u8);
+    response.extend_from_slice(RECORD);
+    response
+}
@@ -0,0 +1,75 @@
+//! Plain DNS lookups against a local name server.
+
+
+extern crate openalias;
+
+use openalias::{DnsResolver, Error, FqdnError, Resolver, ResolverConfig};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+use std::thread;
+
+
+const RECORD: &[u8] = b"oa1:btc recipient_address=1CgLs6CxXMAY4Pj4edQq5vyaFoP9NdqVKH;";
+const SPOOFED: &[u8] = b"oa1:btc recipient_address=1KTexdemPdxSBcG55heUuTjDRYqbC5ZL8H;";
+
+
+#[test]
+fn response_to_other_question_ignored() {
+    let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = sock.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        let (len, from) = sock.recv_from(&mut buf).unwrap();
+        let query = &buf[..len];
+
+        let mut spoofed = query.to_vec();
+        spoofed[12 + 1] = b'x';
+        sock.send_to(&answer(&spoofed, SPOOFED), from).unwrap();
+        sock.send_to(&answer(query, RECORD), from).unwrap();
+    });
+
+    let lookup = resolver(server).lookup("donate.example.com.").unwrap();
+    assert_eq!(lookup.records.len(), 1);
+    assert_eq!(lookup.records[0].data, RECORD);
+}
+
+#[test]
+fn long_label_rejected() {
+    let fqdn = format!("{}.example.com.", "a".repeat(64));
+    match resolver("127.0.0.1:9".parse().unwrap()).lookup(&fqdn) {
+        Err(Error::FqdnParse(FqdnError::LabelTooLong(label))) => assert_eq!(label, "a".repeat(64)),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn long_name_rejected() {
+    let fqdn = format!("{}com.", "abc.".repeat(63));
+    match resolver("127.0.0.1:9".parse().unwrap()).lookup(&fqdn) {
+        Err(Error::FqdnParse(FqdnError::NameTooLong(255))) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+
+fn resolver(server: SocketAddr) -> DnsResolver {
+    DnsResolver::with_config(&ResolverConfig::new().name_server(server).timeout(Duration::from_secs(2)).attempts(1)).unwrap()
+}
+
+/// Echo the query's question, without any OPT record, with the specified text as the answer.
+fn answer(query: &[u8], text: &[u8]) -> Vec<u8> {
+    let mut question_end = 12;
+    while query[question_end] != 0 {
+        question_end += 1 + query[question_end] as usize;
+    }
+    question_end += 5;
+
+    let mut response = query[..2].to_vec();
+    response.extend_from_slice(&[0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]);
+    response.extend_from_slice(&query[12..question_end]);
+    response.extend_from_slice(&[0xC0, 12, 0, 16, 0, 1, 0, 0, 0, 60]);
+    response.extend_from_slice(&(text.len() as u16 + 1).to_be_bytes());
+    response.push(text.len() as u8);
+    response.extend_from_slice(text);
+    response
+}